@@ -0,0 +1,133 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{de::value::StrDeserializer, Deserialize, Serialize};
+use xivc_core::{enums::Job, job};
+
+/// Every action of a single job, as xivc-core knows them.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobActions {
+    // the serialized name of the job, like `DNC`.
+    pub job: String,
+    pub actions: Vec<ActionInfo>,
+}
+
+// the potency, gcd recast and statuses of an action aren't listed, as xivc-core only
+// works them out while a player casts it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ActionInfo {
+    // the name used in configs.
+    pub id: String,
+    pub name: String,
+    pub gcd: bool,
+    pub cd_group: Option<String>,
+    pub cooldown: Option<u32>,
+    pub charges: Option<u8>,
+}
+
+// a deserializer error that keeps the names an enum expected.
+//
+// deserializing an empty string into an enum fails with every valid variant name,
+// which is the only way to list them without xivc-core doing it itself.
+#[derive(Debug)]
+struct Variants(Option<&'static [&'static str]>);
+
+impl fmt::Display for Variants {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::error::Error for Variants {}
+
+impl serde::de::Error for Variants {
+    fn custom<T: fmt::Display>(_: T) -> Self {
+        Self(None)
+    }
+
+    fn unknown_variant(_: &str, expected: &'static [&'static str]) -> Self {
+        Self(Some(expected))
+    }
+}
+
+fn variants(
+    deserialize: impl FnOnce(StrDeserializer<Variants>) -> Result<(), Variants>,
+) -> &'static [&'static str] {
+    match deserialize(StrDeserializer::new("")) {
+        Err(Variants(Some(names))) => names,
+        _ => &[],
+    }
+}
+
+// every action of `job`, along with its serialized name.
+fn named_actions(job: Job) -> Vec<(&'static str, job::Action)> {
+    variants(|d| job::Action::deserialize_for(job, d).map(drop))
+        .iter()
+        .filter_map(|&id| {
+            let action =
+                job::Action::deserialize_for(job, StrDeserializer::<Variants>::new(id)).ok()?;
+            Some((id, action))
+        })
+        .collect()
+}
+
+/// Every action of `job`.
+pub fn job_action_list(job: Job) -> Vec<job::Action> {
+    named_actions(job)
+        .into_iter()
+        .map(|(_, action)| action)
+        .collect()
+}
+
+/// Lists the actions of every job that has them in xivc-core.
+pub fn job_actions() -> Vec<JobActions> {
+    let jobs = variants(|d| Job::deserialize(d).map(drop));
+
+    jobs.iter()
+        .filter_map(|&name| {
+            let job = Job::deserialize(StrDeserializer::<Variants>::new(name)).ok()?;
+            let named = named_actions(job);
+            if named.is_empty() {
+                return None;
+            }
+
+            let actions = named
+                .into_iter()
+                .map(|(id, action)| {
+                    let cd = action.cd_info();
+                    ActionInfo {
+                        id: id.to_string(),
+                        name: action.name().to_string(),
+                        gcd: action.gcd(),
+                        cd_group: cd.map(|(group, ..)| format!("{:?}", group)),
+                        cooldown: cd.map(|(_, cd, _)| cd as u32),
+                        charges: cd.map(|(.., charges)| charges as u8),
+                    }
+                })
+                .collect();
+
+            Some(JobActions {
+                job: name.to_string(),
+                actions,
+            })
+        })
+        .collect()
+}
+
+/// Writes `<job>.txt` with the name of every action for each job into `dir`,
+/// along with everything known about them in `actions.json`.
+pub fn write_lists(dir: &Path) -> io::Result<()> {
+    let jobs = job_actions();
+    fs::create_dir_all(dir)?;
+
+    for job in &jobs {
+        let mut list = String::new();
+        for action in &job.actions {
+            list.push_str(&action.id);
+            list.push('\n');
+        }
+        fs::write(dir.join(format!("{}.txt", job.job.to_lowercase())), list)?;
+    }
+
+    let json = serde_json::to_string_pretty(&jobs).map_err(io::Error::other)?;
+    fs::write(dir.join("actions.json"), json)
+}
@@ -0,0 +1,736 @@
+use std::fmt;
+
+use xivc_core::{
+    enums::Job,
+    job::CdGroup,
+    world::{Action, ActorId},
+};
+
+use crate::{actions, DamageCategory, DamageLedger, ReportKind, StatusReportKind, WorldState};
+
+/// A summary of where a single player's rotation lost time.
+#[derive(Clone, Debug)]
+pub struct RotationSummary {
+    pub name: String,
+    // the number of gcds used after combat started.
+    pub gcds: u32,
+    // the number of gcds that started later than their recast allowed.
+    pub clipped: u32,
+    // the total time gcds were delayed past their recast.
+    pub clip_time: u32,
+    // the number of weaves whose animation lock ran past the next gcd.
+    pub late_weaves: u32,
+    // the part of the clipping caused by animation lock.
+    pub weave_time: u32,
+    // the time the gcd was ready, but nothing was used or locking the player.
+    pub idle_time: u32,
+    pub cooldowns: Vec<CooldownDrift>,
+}
+
+/// How far the uses of a cooldown group drifted from using it on cooldown.
+#[derive(Clone, Debug)]
+pub struct CooldownDrift {
+    pub group: CdGroup,
+    pub uses: u32,
+    // the total time the group was usable without being used.
+    pub drift: u32,
+    pub max_drift: u32,
+    // when the group is usable again.
+    ready: u32,
+}
+
+// every cooldown group the actions of `job` use.
+fn job_groups(job: Job) -> Vec<CdGroup> {
+    let mut groups = Vec::new();
+    for action in actions::job_action_list(job) {
+        if let Some((group, ..)) = action.cd_info() {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+    }
+    groups
+}
+
+pub fn is_gcd(action: &Action) -> bool {
+    match action {
+        Action::Job(action) => action.gcd(),
+    }
+}
+
+/// Analyzes the rotation of every player from the `CastStart` reports in `log`.
+///
+/// Only time after combat starts and before `end` is counted.
+pub fn rotation(log: &[(u32, ReportKind)], world: &WorldState, end: u32) -> Vec<RotationSummary> {
+    world
+        .actors
+        .iter()
+        .enumerate()
+        .filter_map(|(id, actor)| Some((id, actor, actor.player.as_ref()?)))
+        .map(|(id, actor, player)| {
+            player_rotation(
+                log,
+                ActorId(id as u16),
+                actor.name.clone(),
+                job_groups(player.job_kind),
+                world.in_combat,
+                end,
+            )
+        })
+        .collect()
+}
+
+fn player_rotation(
+    log: &[(u32, ReportKind)],
+    id: ActorId,
+    name: String,
+    groups: Vec<CdGroup>,
+    start: u32,
+    end: u32,
+) -> RotationSummary {
+    let mut summary = RotationSummary {
+        name,
+        gcds: 0,
+        clipped: 0,
+        clip_time: 0,
+        late_weaves: 0,
+        weave_time: 0,
+        idle_time: 0,
+        // every group starts out ready when combat starts, so holding one is drift too.
+        cooldowns: groups
+            .into_iter()
+            .map(|group| CooldownDrift {
+                group,
+                uses: 0,
+                drift: 0,
+                max_drift: 0,
+                ready: start,
+            })
+            .collect(),
+    };
+
+    // when the next gcd could have been used.
+    let mut gcd_ready = start;
+    // when the latest animation lock ends.
+    let mut lock_end = 0;
+    // when the lock of each weave since the last gcd ends.
+    let mut weaves = Vec::new();
+
+    for (time, kind) in log {
+        let ReportKind::CastStart {
+            source,
+            action,
+            recast,
+            lock,
+            cooldowns,
+        } = kind
+        else {
+            continue;
+        };
+        let time = *time;
+        if *source != id || time >= end {
+            continue;
+        }
+
+        for &(group, until) in cooldowns {
+            let index = match summary.cooldowns.iter().position(|cd| cd.group == group) {
+                Some(index) => index,
+                None => {
+                    summary.cooldowns.push(CooldownDrift {
+                        group,
+                        uses: 0,
+                        drift: 0,
+                        max_drift: 0,
+                        ready: start,
+                    });
+                    summary.cooldowns.len() - 1
+                }
+            };
+            let cd = &mut summary.cooldowns[index];
+            // uses before the pull only move when the group is ready again.
+            if time >= start {
+                let drift = time.saturating_sub(cd.ready.max(start));
+                cd.uses += 1;
+                cd.drift += drift;
+                cd.max_drift = cd.max_drift.max(drift);
+            }
+            cd.ready = time + until;
+        }
+
+        if is_gcd(action) {
+            let ready = gcd_ready.max(start);
+            if time >= start {
+                summary.gcds += 1;
+                if time > ready {
+                    let clip = time - ready;
+                    let weave = lock_end.saturating_sub(ready).min(clip);
+
+                    summary.clipped += 1;
+                    summary.clip_time += clip;
+                    summary.weave_time += weave;
+                    summary.idle_time += clip - weave;
+                    summary.late_weaves += weaves.iter().filter(|&&v| v > ready).count() as u32;
+                }
+            }
+            gcd_ready = time + *recast as u32;
+            weaves.clear();
+        } else {
+            weaves.push(time + *lock as u32);
+        }
+        lock_end = lock_end.max(time + *lock as u32);
+    }
+
+    // anything left unused at the end of the fight.
+    summary.idle_time += end.saturating_sub(gcd_ready.max(lock_end).max(start));
+    for cd in &mut summary.cooldowns {
+        let drift = end.saturating_sub(cd.ready.max(start));
+        cd.drift += drift;
+        cd.max_drift = cd.max_drift.max(drift);
+    }
+
+    summary
+}
+
+struct Secs(u32);
+
+impl fmt::Display for Secs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{}.{:03}s", self.0 / 1000, self.0 % 1000))
+    }
+}
+
+impl fmt::Display for RotationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rotation summary for {}:", self.name)?;
+        writeln!(f, "    gcds: {}", self.gcds)?;
+        writeln!(
+            f,
+            "    clipping: {} over {} gcds",
+            Secs(self.clip_time),
+            self.clipped
+        )?;
+        writeln!(
+            f,
+            "        from weaves: {} ({} late weaves)",
+            Secs(self.weave_time),
+            self.late_weaves
+        )?;
+        writeln!(f, "    idle: {}", Secs(self.idle_time))?;
+        writeln!(
+            f,
+            "    {:<24} {:>6} {:>12} {:>12}",
+            "cooldown", "uses", "drift", "max drift"
+        )?;
+        for cd in &self.cooldowns {
+            writeln!(
+                f,
+                "    {:<24} {:>6} {:>12} {:>12}",
+                format!("{:?}", cd.group),
+                cd.uses,
+                Secs(cd.drift),
+                Secs(cd.max_drift)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How a single player's mp changed over the fight.
+#[derive(Clone, Debug)]
+pub struct MpTimeline {
+    pub name: String,
+    // the time since combat started and the mp after every change.
+    pub points: Vec<(u32, u16)>,
+    pub lowest: u16,
+    // the mp from ticks that was lost to being at the cap.
+    pub wasted: u32,
+}
+
+/// Builds the mp timeline of every player from the `MpTick` and `MpChange` reports in `log`.
+///
+/// Only changes after combat starts and before `end` are counted.
+pub fn mp_timeline(log: &[(u32, ReportKind)], world: &WorldState, end: u32) -> Vec<MpTimeline> {
+    world
+        .actors
+        .iter()
+        .enumerate()
+        .filter_map(|(id, actor)| Some((ActorId(id as u16), actor, actor.player.as_ref()?)))
+        .map(|(id, actor, player)| {
+            let mut timeline = MpTimeline {
+                name: actor.name.clone(),
+                points: Vec::new(),
+                lowest: player.mp,
+                wasted: 0,
+            };
+            for (time, kind) in log {
+                if *time < world.in_combat || *time >= end {
+                    continue;
+                }
+                let (to, wasted) = match *kind {
+                    ReportKind::MpTick {
+                        actor,
+                        from,
+                        to,
+                        tick,
+                    } if actor == id => (to, tick - (to - from)),
+                    ReportKind::MpChange { actor, to, .. } if actor == id => (to, 0),
+                    _ => continue,
+                };
+                timeline.wasted += wasted as u32;
+                timeline.lowest = timeline.lowest.min(to);
+                timeline.points.push((time - world.in_combat, to));
+            }
+            timeline
+        })
+        .collect()
+}
+
+impl fmt::Display for MpTimeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "mp timeline for {}:", self.name)?;
+        writeln!(f, "    lowest: {}", self.lowest)?;
+        writeln!(f, "    wasted at cap: {}", self.wasted)?;
+        for (time, mp) in &self.points {
+            writeln!(f, "    {:>12} {:>6}", Secs(*time), mp)?;
+        }
+        Ok(())
+    }
+}
+
+/// The damage a single player dealt, split up by target, category and source.
+pub struct DamageBreakdown<'w> {
+    name: &'w str,
+    world: &'w WorldState,
+    ledger: &'w DamageLedger,
+    end: u32,
+}
+
+/// The damage breakdown of every player, with dps over the time between combat starting and `end`.
+pub fn damage_breakdown(world: &WorldState, end: u32) -> Vec<DamageBreakdown<'_>> {
+    world
+        .actors
+        .iter()
+        .filter(|actor| actor.player.is_some())
+        .map(|actor| DamageBreakdown {
+            name: &actor.name,
+            world,
+            ledger: &actor.dealt,
+            end,
+        })
+        .collect()
+}
+
+impl fmt::Display for DamageBreakdown<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ledger = self.ledger;
+        let percent = |damage: u64| damage as f64 / ledger.total.max(1) as f64 * 100.0;
+
+        writeln!(
+            f,
+            "damage breakdown for {}: {} ({:.2} dps)",
+            self.name,
+            ledger.total,
+            ledger.dps(self.world, self.end)
+        )?;
+        for (name, category) in [
+            ("direct", DamageCategory::Direct),
+            ("dot", DamageCategory::Dot),
+            ("auto", DamageCategory::Auto),
+        ] {
+            let damage = ledger.of_category(category);
+            writeln!(
+                f,
+                "    {:<8} {:>12} {:>7.2}%",
+                name,
+                damage,
+                percent(damage)
+            )?;
+        }
+
+        let mut targets: Vec<ActorId> = Vec::new();
+        for entry in &ledger.entries {
+            if !targets.contains(&entry.target) {
+                targets.push(entry.target);
+            }
+        }
+        for target in targets {
+            let mut entries: Vec<_> = ledger
+                .entries
+                .iter()
+                .filter(|v| v.target == target)
+                .collect();
+            entries.sort_by(|a, b| b.damage.cmp(&a.damage));
+
+            writeln!(
+                f,
+                "    to {}: {}",
+                self.world.actors[target.0 as usize].name,
+                ledger.to_target(target)
+            )?;
+            writeln!(
+                f,
+                "        {:<24} {:>6} {:>12} {:>8}",
+                "source", "hits", "damage", "share"
+            )?;
+            for entry in entries {
+                writeln!(
+                    f,
+                    "        {:<24} {:>6} {:>12} {:>7.2}%",
+                    entry.source.name(),
+                    entry.hits,
+                    entry.damage,
+                    percent(entry.damage)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tracks how well a set of buffs lined up with damage, summed over every run.
+#[derive(Clone, Debug)]
+pub struct BuffAlignment {
+    statuses: Vec<String>,
+    runs: u32,
+    // the time in combat, summed over every run.
+    duration: u64,
+    entries: Vec<BuffEntry>,
+    // all damage dealt by each player in combat, summed over every run.
+    totals: Vec<(String, u64)>,
+}
+
+#[derive(Clone, Debug)]
+struct BuffEntry {
+    status: String,
+    source: String,
+    receiver: String,
+    applications: u64,
+    uptime: u64,
+    gcds: u64,
+    ogcds: u64,
+    // damage dealt by the receiver that snapshotted while the buff was up.
+    inside: u64,
+    // the time each other buff overlapped with this one.
+    overlaps: Vec<(String, u64)>,
+}
+
+// a single period of time a buff was on its receiver.
+struct BuffWindow {
+    status: String,
+    source: ActorId,
+    receiver: ActorId,
+    start: u32,
+    end: u32,
+}
+
+// a single hit or dot tick, along with when its damage was snapshotted.
+struct Hit {
+    source: ActorId,
+    snapshot: u32,
+    damage: u64,
+}
+
+// every hit that landed between `start` and `end`.
+//
+// casts snapshot when the `CastSnap` is reported, which can be well before the damage lands,
+// and dots snapshot when they are applied. auto attacks snapshot when they land.
+fn hits(log: &[(u32, ReportKind)], start: u32, end: u32) -> Vec<Hit> {
+    let mut snaps: Vec<(ActorId, Action, u32)> = Vec::new();
+    let mut dots: Vec<(ActorId, ActorId, &str, u32)> = Vec::new();
+    let mut hits = Vec::new();
+
+    for (time, kind) in log {
+        let time = *time;
+        let (source, snapshot, damage) = match kind {
+            ReportKind::CastSnap { source, action } => {
+                match snaps.iter_mut().find(|v| v.0 == *source && v.1 == *action) {
+                    Some(v) => v.2 = time,
+                    None => snaps.push((*source, *action, time)),
+                }
+                continue;
+            }
+            ReportKind::Status {
+                status,
+                source,
+                target,
+                kind: StatusReportKind::Apply { .. },
+            } => {
+                match dots
+                    .iter_mut()
+                    .find(|v| v.0 == *source && v.1 == *target && v.2 == status.name)
+                {
+                    Some(v) => v.3 = time,
+                    None => dots.push((*source, *target, status.name, time)),
+                }
+                continue;
+            }
+            ReportKind::Damage {
+                source,
+                action,
+                damage,
+                ..
+            } => {
+                let snapshot = action.and_then(|action| {
+                    snaps
+                        .iter()
+                        .find(|v| v.0 == *source && v.1 == action)
+                        .map(|v| v.2)
+                });
+                (*source, snapshot.unwrap_or(time), *damage)
+            }
+            ReportKind::DotTick {
+                source,
+                target,
+                status,
+                damage,
+            } => {
+                let snapshot = dots
+                    .iter()
+                    .find(|v| v.0 == *source && v.1 == *target && v.2 == status.name)
+                    .map(|v| v.3);
+                (*source, snapshot.unwrap_or(time), *damage)
+            }
+            _ => continue,
+        };
+        if time >= start && time < end {
+            hits.push(Hit {
+                source,
+                snapshot,
+                damage,
+            });
+        }
+    }
+    hits
+}
+
+impl BuffAlignment {
+    pub fn new(statuses: Vec<String>) -> Self {
+        Self {
+            statuses,
+            runs: 0,
+            duration: 0,
+            entries: Vec::new(),
+            totals: Vec::new(),
+        }
+    }
+
+    /// The average number of gcds `receiver` used under `status` per run.
+    pub fn gcds(&self, status: &str, receiver: &str) -> f64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == status && entry.receiver == receiver)
+            .map(|entry| entry.gcds)
+            .sum::<u64>() as f64
+            / self.runs.max(1) as f64
+    }
+
+    pub fn add_run(&mut self, log: &[(u32, ReportKind)], world: &WorldState, end: u32) {
+        let start = world.in_combat;
+        let windows = buff_windows(log, &self.statuses, start, end);
+        let hits = hits(log, start, end);
+
+        self.runs += 1;
+        self.duration += end.saturating_sub(start) as u64;
+
+        let name = |id: ActorId| world.actors[id.0 as usize].name.clone();
+
+        for window in &windows {
+            let inside = |time: u32| time >= window.start && time < window.end;
+
+            let index = match self.entries.iter().position(|entry| {
+                entry.status == window.status
+                    && entry.source == name(window.source)
+                    && entry.receiver == name(window.receiver)
+            }) {
+                Some(index) => index,
+                None => {
+                    self.entries.push(BuffEntry {
+                        status: window.status.clone(),
+                        source: name(window.source),
+                        receiver: name(window.receiver),
+                        applications: 0,
+                        uptime: 0,
+                        gcds: 0,
+                        ogcds: 0,
+                        inside: 0,
+                        overlaps: Vec::new(),
+                    });
+                    self.entries.len() - 1
+                }
+            };
+            let entry = &mut self.entries[index];
+
+            entry.applications += 1;
+            entry.uptime += (window.end - window.start) as u64;
+
+            for (time, kind) in log {
+                match kind {
+                    ReportKind::CastStart { source, action, .. }
+                        if *source == window.receiver && inside(*time) =>
+                    {
+                        if is_gcd(action) {
+                            entry.gcds += 1;
+                        } else {
+                            entry.ogcds += 1;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            entry.inside += hits
+                .iter()
+                .filter(|hit| hit.source == window.receiver && inside(hit.snapshot))
+                .map(|hit| hit.damage)
+                .sum::<u64>();
+
+            for other in &windows {
+                if other.receiver != window.receiver || other.status == window.status {
+                    continue;
+                }
+                let overlap = window
+                    .end
+                    .min(other.end)
+                    .saturating_sub(window.start.max(other.start));
+                if overlap == 0 {
+                    continue;
+                }
+                match entry.overlaps.iter_mut().find(|v| v.0 == other.status) {
+                    Some(v) => v.1 += overlap as u64,
+                    None => entry.overlaps.push((other.status.clone(), overlap as u64)),
+                }
+            }
+        }
+
+        // totals are kept for every player, even in runs where they got no buffs.
+        for (id, actor) in world.actors.iter().enumerate() {
+            if actor.player.is_none() {
+                continue;
+            }
+            let damage: u64 = hits
+                .iter()
+                .filter(|hit| hit.source == ActorId(id as u16))
+                .map(|hit| hit.damage)
+                .sum();
+            match self.totals.iter_mut().find(|v| v.0 == actor.name) {
+                Some(v) => v.1 += damage,
+                None => self.totals.push((actor.name.clone(), damage)),
+            }
+        }
+    }
+
+    // all damage dealt by `receiver`, summed over every run.
+    fn total(&self, receiver: &str) -> u64 {
+        self.totals
+            .iter()
+            .find(|v| v.0 == receiver)
+            .map(|v| v.1)
+            .unwrap_or_default()
+    }
+}
+
+// finds every period one of `statuses` was up, clamped to between `start` and `end`.
+fn buff_windows(
+    log: &[(u32, ReportKind)],
+    statuses: &[String],
+    start: u32,
+    end: u32,
+) -> Vec<BuffWindow> {
+    let mut windows = Vec::new();
+    let mut active: Vec<BuffWindow> = Vec::new();
+
+    for (time, kind) in log {
+        let ReportKind::Status {
+            status,
+            source,
+            target,
+            kind,
+        } = kind
+        else {
+            continue;
+        };
+        if !statuses.iter().any(|v| *v == status.name) {
+            continue;
+        }
+        let position = active
+            .iter()
+            .position(|v| v.status == status.name && v.receiver == *target);
+        match kind {
+            StatusReportKind::Apply { .. }
+            | StatusReportKind::ExtendDuration { .. }
+            | StatusReportKind::AddStacks { .. } => {
+                if position.is_none() {
+                    active.push(BuffWindow {
+                        status: status.name.to_string(),
+                        source: *source,
+                        receiver: *target,
+                        start: *time,
+                        end,
+                    });
+                }
+            }
+            StatusReportKind::Remove | StatusReportKind::NaturalRemove => {
+                if let Some(position) = position {
+                    let mut window = active.swap_remove(position);
+                    window.end = *time;
+                    windows.push(window);
+                }
+            }
+            // removing stacks doesn't say if the status is gone, so it's ignored.
+            StatusReportKind::RemoveStacks { .. } => (),
+        }
+    }
+    windows.extend(active);
+
+    windows
+        .into_iter()
+        .filter_map(|mut window| {
+            window.start = window.start.max(start);
+            window.end = window.end.min(end);
+            (window.start < window.end).then_some(window)
+        })
+        .collect()
+}
+
+impl fmt::Display for BuffAlignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let runs = self.runs.max(1) as u64;
+        writeln!(f, "buff alignment over {} runs (per run):", self.runs)?;
+        for entry in &self.entries {
+            let total = self.total(&entry.receiver);
+            writeln!(
+                f,
+                "{} from {} on {}",
+                entry.status, entry.source, entry.receiver
+            )?;
+            writeln!(
+                f,
+                "    uptime: {} ({:.2}%) over {:.2} applications",
+                Secs((entry.uptime / runs) as u32),
+                entry.uptime as f64 / self.duration.max(1) as f64 * 100.0,
+                entry.applications as f64 / runs as f64
+            )?;
+            writeln!(
+                f,
+                "    covered: {:.2} gcds, {:.2} ogcds",
+                entry.gcds as f64 / runs as f64,
+                entry.ogcds as f64 / runs as f64
+            )?;
+            writeln!(
+                f,
+                "    damage: {} inside ({:.2}%), {} outside",
+                entry.inside / runs,
+                entry.inside as f64 / total.max(1) as f64 * 100.0,
+                total.saturating_sub(entry.inside) / runs
+            )?;
+            for (status, overlap) in &entry.overlaps {
+                writeln!(
+                    f,
+                    "    overlapped with {} for {}",
+                    status,
+                    Secs((overlap / runs) as u32)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
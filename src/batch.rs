@@ -0,0 +1,382 @@
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use xivc_core::{math::XivMath, world::ActorId};
+
+use crate::{
+    analysis::{self, BuffAlignment},
+    data::{PlayerData, ReportConfig, SimData, StatDelta},
+    ReportKind, Simulation,
+};
+
+/// The mean of a set of runs, and the half width of its 95% confidence interval.
+#[derive(Clone, Copy, Debug)]
+pub struct Summary {
+    pub mean: f64,
+    pub ci: f64,
+}
+
+impl Summary {
+    pub fn new(values: &[f64]) -> Self {
+        let n = values.len() as f64;
+        if values.len() < 2 {
+            return Self {
+                mean: values.first().copied().unwrap_or_default(),
+                ci: 0.0,
+            };
+        }
+        let mean = values.iter().sum::<f64>() / n;
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Self {
+            mean,
+            ci: 1.96 * (var / n).sqrt(),
+        }
+    }
+}
+
+/// The largest seed a config can have, as toml can't hold a bigger integer.
+pub const MAX_SEED: u64 = i64::MAX as u64;
+
+/// The seed of the first run of a batch.
+///
+/// A random seed is at most [`MAX_SEED`], so it can be copied into any config.
+pub fn base_seed(data: &SimData) -> u64 {
+    data.seed
+        .unwrap_or_else(|| thread_rng().gen_range(0..=MAX_SEED))
+}
+
+/// Runs a single simulation to completion without printing any reports.
+///
+/// # Panics
+/// If `data` can't be built into a simulation, which [`Simulation::from_sim_data`] checks.
+pub fn simulate(data: &SimData, seed: u64) -> Simulation {
+    let mut data = data.clone();
+    data.report = ReportConfig::default();
+    if data.random_tick_phase {
+        tick_phases(&mut data, seed);
+    }
+
+    let mut sim = Simulation::from_sim_data(data, seed).expect("the config can't be simulated");
+    sim.run_fight();
+    sim
+}
+
+/// Moves the server tick of every enemy to a random point in its 3 second cycle.
+///
+/// The phase only depends on `seed`, so runs with the same seed tick at the same time.
+pub fn tick_phases(data: &mut SimData, seed: u64) {
+    let mut rng = Pcg64::seed_from_u64(seed);
+    for enemy in &mut data.enemies {
+        enemy.first_actor_tick = rng.gen_range(0..3000);
+    }
+}
+
+/// The damage per second dealt by every player together over the whole fight.
+pub fn dps(sim: &Simulation) -> f64 {
+    sim.world
+        .actors
+        .iter()
+        .filter(|actor| actor.player.is_some())
+        .map(|actor| actor.dealt.dps(&sim.world, sim.end()))
+        .sum()
+}
+
+/// The damage per second dealt by the player `index` over the whole fight.
+pub fn player_dps(sim: &Simulation, index: usize) -> f64 {
+    sim.world.actors[index].dealt.dps(&sim.world, sim.end())
+}
+
+/// Runs `data.runs` simulations starting at `seed`, returning the dps of the player `index`
+/// in each one.
+///
+/// Using the same seed for different configs gives both the same random numbers,
+/// so the difference between them is less noisy.
+pub fn dps_runs(data: &SimData, seed: u64, index: usize) -> Vec<f64> {
+    (0..data.runs)
+        .map(|run| player_dps(&simulate(data, seed.wrapping_add(run as u64)), index))
+        .collect()
+}
+
+/// Compares every gear set of every player against their configured stats.
+pub fn compare(data: &SimData) {
+    let seed = base_seed(data);
+    eprintln!("seed: {}", seed);
+
+    for (index, player) in data.players.iter().enumerate() {
+        if player.gearsets.is_empty() {
+            continue;
+        }
+        let base = dps_runs(data, seed, index);
+        let summary = Summary::new(&base);
+
+        println!("gear comparison for {} ({} runs):", player.name, data.runs);
+        println!(
+            "    {:<24} {:>10} {:>10} {:>10} {:>10}",
+            "set", "dps", "±", "delta", "±"
+        );
+        println!(
+            "    {:<24} {:>10.2} {:>10.2} {:>10} {:>10}",
+            "current", summary.mean, summary.ci, "", ""
+        );
+
+        for set in &player.gearsets {
+            let mut data = data.clone();
+            set.apply(&mut data.players[index]);
+
+            let values = dps_runs(&data, seed, index);
+            let deltas: Vec<f64> = values.iter().zip(&base).map(|(a, b)| a - b).collect();
+
+            let summary = Summary::new(&values);
+            let delta = Summary::new(&deltas);
+
+            println!(
+                "    {:<24} {:>10.2} {:>10.2} {:>+10.2} {:>10.2}",
+                set.name, summary.mean, summary.ci, delta.mean, delta.ci
+            );
+        }
+    }
+}
+
+/// Finds the dps each point of a stat is worth for every player, relative to their main stat.
+///
+/// Each stat is moved both up and down by at least its step, and the weight is the
+/// difference in dps divided by the distance between the two. Crit and direct hit are
+/// moved to the start of a tier, so both ends sit in the same place relative to a breakpoint.
+pub fn weights(data: &SimData) {
+    let seed = base_seed(data);
+    eprintln!("seed: {}", seed);
+
+    let steps = data.weight_steps;
+    let unit = StatDelta::default();
+    let stats: [(&str, i32, StatDelta, Option<TierProbe>); 6] = [
+        ("main", steps.main, StatDelta { main: 1, ..unit }, None),
+        (
+            "crt",
+            steps.crt,
+            StatDelta { crt: 1, ..unit },
+            Some(crit_tier),
+        ),
+        ("det", steps.det, StatDelta { det: 1, ..unit }, None),
+        ("dh", steps.dh, StatDelta { dh: 1, ..unit }, Some(dhit_tier)),
+        ("sks", steps.sks, StatDelta { sks: 1, ..unit }, None),
+        ("wd", steps.wd, StatDelta { wd: 1, ..unit }, None),
+    ];
+
+    for (index, player) in data.players.iter().enumerate() {
+        let results: Vec<_> = stats
+            .iter()
+            .map(|&(name, step, delta, probe)| {
+                let (minus, plus) = match probe {
+                    Some(probe) => (
+                        tier_start(player, delta, probe, -step, -1),
+                        tier_start(player, delta, probe, step, 1),
+                    ),
+                    None => (-step, step),
+                };
+
+                let run = |offset: i32| {
+                    let mut data = data.clone();
+                    let player = &mut data.players[index];
+                    delta
+                        .scale(offset)
+                        .apply(&mut player.stats, &mut player.weapon);
+                    dps_runs(&data, seed, index)
+                };
+
+                let span = (plus - minus).max(1) as f64;
+                let values: Vec<f64> = run(plus)
+                    .iter()
+                    .zip(&run(minus))
+                    .map(|(a, b)| (a - b) / span)
+                    .collect();
+
+                (name, minus, plus, Summary::new(&values))
+            })
+            .collect();
+
+        let main = results[0].3.mean;
+
+        println!(
+            "stat weights for {} ({} runs, relative to main stat):",
+            player.name, data.runs
+        );
+        println!(
+            "    {:<6} {:>12} {:>10} {:>10} {:>10}",
+            "stat", "range", "dps/point", "±", "weight"
+        );
+        for (name, minus, plus, summary) in results {
+            println!(
+                "    {:<6} {:>12} {:>10.4} {:>10.4} {:>10.4}",
+                name,
+                format!("{}/+{}", minus, plus),
+                summary.mean,
+                summary.ci,
+                summary.mean / main
+            );
+        }
+    }
+}
+
+// something that changes exactly when a stat reaches a new tier.
+type TierProbe = fn(&XivMath) -> u64;
+
+fn crit_tier(math: &XivMath) -> u64 {
+    math.crit_chance() as u64
+}
+
+fn dhit_tier(math: &XivMath) -> u64 {
+    math.dhit_chance() as u64
+}
+
+// finds the first offset from `from` in the direction of `dir` where the stat starts a new tier.
+fn tier_start(player: &PlayerData, delta: StatDelta, probe: TierProbe, from: i32, dir: i32) -> i32 {
+    let value = |offset: i32| {
+        let mut stats = player.stats.clone();
+        let mut weapon = player.weapon.clone();
+        delta.scale(offset).apply(&mut stats, &mut weapon);
+        probe(&XivMath::new(stats, weapon, player.player_info.clone()))
+    };
+
+    let mut offset = from;
+    // tiers are never this wide, so something has gone wrong if this runs out.
+    for _ in 0..500 {
+        if value(offset) != value(offset - 1) {
+            return offset;
+        }
+        offset += dir;
+    }
+    from
+}
+
+// the results of every skill speed value that ended up with the same gcd.
+struct SksTier {
+    gcd: u16,
+    min: u16,
+    max: u16,
+    dps: Vec<f64>,
+    gcds: f64,
+    buff_gcds: Vec<f64>,
+    // how many skill speed values are in this tier.
+    count: u32,
+}
+
+/// Runs every player across a range of skill speed values, grouped by the gcd they end up with.
+pub fn sks_sweep(data: &SimData) {
+    let seed = base_seed(data);
+    eprintln!("seed: {}", seed);
+
+    let sweep = data.sks_sweep;
+    let buffs = &data.report.buffs;
+
+    for (index, player) in data.players.iter().enumerate() {
+        let mut tiers: Vec<SksTier> = Vec::new();
+
+        for sks in (sweep.from..=sweep.to).step_by(sweep.step.max(1) as usize) {
+            let mut data = data.clone();
+            data.players[index].stats.sks = sks;
+
+            let mut dps_values = Vec::new();
+            let mut gcds = 0;
+            let mut alignment = BuffAlignment::new(buffs.clone());
+            let mut gcd = 0;
+
+            for run in 0..data.runs {
+                let sim = simulate(&data, seed.wrapping_add(run as u64));
+                let end = sim.end();
+                dps_values.push(player_dps(&sim, index));
+                gcds += analysis::rotation(&sim.log, &sim.world, end)
+                    .get(index)
+                    .map(|v| v.gcds)
+                    .unwrap_or_default();
+                alignment.add_run(&sim.log, &sim.world, end);
+                if run == 0 {
+                    gcd = common_recast(&sim.log, ActorId(index as u16));
+                }
+            }
+
+            let runs = data.runs.max(1) as f64;
+            let gcds = gcds as f64 / runs;
+            let buff_gcds: Vec<f64> = buffs
+                .iter()
+                .map(|buff| alignment.gcds(buff, &player.name))
+                .collect();
+
+            match tiers.last_mut() {
+                Some(tier) if tier.gcd == gcd => {
+                    tier.max = sks;
+                    tier.dps.extend(dps_values);
+                    tier.gcds += gcds;
+                    tier.buff_gcds
+                        .iter_mut()
+                        .zip(buff_gcds)
+                        .for_each(|(a, b)| *a += b);
+                    tier.count += 1;
+                }
+                _ => tiers.push(SksTier {
+                    gcd,
+                    min: sks,
+                    max: sks,
+                    dps: dps_values,
+                    gcds,
+                    buff_gcds,
+                    count: 1,
+                }),
+            }
+        }
+
+        println!(
+            "skill speed tiers for {} ({} runs each):",
+            player.name, data.runs
+        );
+        print!(
+            "    {:>6} {:>12} {:>10} {:>10} {:>8}",
+            "gcd", "sks", "dps", "±", "gcds"
+        );
+        for buff in buffs {
+            print!(" {:>20}", buff);
+        }
+        println!();
+        for tier in &tiers {
+            let summary = Summary::new(&tier.dps);
+            let count = tier.count as f64;
+            print!(
+                "    {:>6.2} {:>12} {:>10.2} {:>10.2} {:>8.2}",
+                tier.gcd as f64 / 1000.0,
+                format!("{}-{}", tier.min, tier.max),
+                summary.mean,
+                summary.ci,
+                tier.gcds / count
+            );
+            for gcds in &tier.buff_gcds {
+                print!(" {:>20.2}", gcds / count);
+            }
+            println!();
+        }
+    }
+}
+
+// the gcd recast `actor` used most often, which is the one for their normal gcds.
+fn common_recast(log: &[(u32, ReportKind)], actor: ActorId) -> u16 {
+    let mut counts: Vec<(u16, u32)> = Vec::new();
+    for (_, kind) in log {
+        if let ReportKind::CastStart {
+            source,
+            action,
+            recast,
+            ..
+        } = kind
+        {
+            if *source != actor || !analysis::is_gcd(action) {
+                continue;
+            }
+            match counts.iter_mut().find(|v| v.0 == *recast) {
+                Some(v) => v.1 += 1,
+                None => counts.push((*recast, 1)),
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|v| v.1)
+        .map(|v| v.0)
+        .unwrap_or_default()
+}
@@ -0,0 +1,201 @@
+use std::{fmt, path::Path};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::data::SimData;
+
+/// A format a config can be written in, all of which share the same model in [`SimData`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ycf,
+    Toml,
+    Json,
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    // the extension, which isn't one of the formats.
+    UnknownFormat(String),
+    Parse(Format, String),
+    Write(Format, String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat(ext) => write!(
+                f,
+                "unknown config format `{}`. expected one of `ycf`, `toml`, `json`",
+                ext
+            ),
+            Self::Parse(format, e) => write!(f, "invalid {} config: {}", format, e),
+            Self::Write(format, e) => write!(f, "couldn't write a {} config: {}", format, e),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ycf => "ycf",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        })
+    }
+}
+
+impl Format {
+    /// The format of the file at `path`, from its extension.
+    pub fn from_path(path: &Path) -> Result<Self, FormatError> {
+        let ext = path
+            .extension()
+            .and_then(|v| v.to_str())
+            .unwrap_or_default();
+        match ext.to_lowercase().as_str() {
+            "ycf" => Ok(Self::Ycf),
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            _ => Err(FormatError::UnknownFormat(ext.to_string())),
+        }
+    }
+
+    pub fn parse(self, source: &str) -> Result<SimData, FormatError> {
+        let error = |e: &dyn fmt::Display| FormatError::Parse(self, e.to_string());
+        match self {
+            Self::Ycf => {
+                let mut deserializer = ycf::de::TopDeserializer::from_str(source);
+                SimData::deserialize(&mut deserializer).map_err(|e| error(&e))
+            }
+            Self::Toml => toml::from_str(source).map_err(|e| error(&e)),
+            Self::Json => serde_json::from_str(source).map_err(|e| error(&e)),
+        }
+    }
+
+    /// The path of every key in `source` that isn't part of the config, like `players[0].in_comabt`.
+    ///
+    /// These are ignored when parsing, so a misspelled key would otherwise do nothing.
+    /// `source` should already have parsed, as errors aren't returned here.
+    pub fn unknown_keys(self, source: &str) -> Vec<String> {
+        let mut keys = Vec::new();
+        let mut unknown = |path: serde_ignored::Path| keys.push(key_path(&path));
+        let _: Option<SimData> = match self {
+            Self::Ycf => {
+                let mut deserializer = ycf::de::TopDeserializer::from_str(source);
+                serde_ignored::deserialize(&mut deserializer, &mut unknown).ok()
+            }
+            Self::Toml => {
+                serde_ignored::deserialize(toml::Deserializer::new(source), &mut unknown).ok()
+            }
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_str(source);
+                serde_ignored::deserialize(&mut deserializer, &mut unknown).ok()
+            }
+        };
+        keys
+    }
+
+    /// Writes `data` in this format. Every field is written, including the ones left at their default.
+    pub fn write(self, data: &SimData) -> Result<String, FormatError> {
+        let error = |e: &dyn fmt::Display| FormatError::Write(self, e.to_string());
+        match self {
+            Self::Ycf => {
+                let value = serde_json::to_value(data).map_err(|e| error(&e))?;
+                let mut out = String::new();
+                match value {
+                    Value::Object(fields) => ycf_fields(&mut out, &fields, 0),
+                    _ => unreachable!("a config is always a struct"),
+                }
+                Ok(out)
+            }
+            Self::Toml => toml::to_string_pretty(data).map_err(|e| error(&e)),
+            Self::Json => serde_json::to_string_pretty(data).map_err(|e| error(&e)),
+        }
+    }
+}
+
+// writes a path the same way as the errors from validating, like `players[0].stats.crt`.
+fn key_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", key_path(parent), index),
+        Path::Map { parent, key } => match key_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => key_path(parent),
+    }
+}
+
+// ycf has no serializer, so the config goes through the json model first.
+
+const INDENT: &str = "    ";
+
+fn ycf_fields(out: &mut String, fields: &serde_json::Map<String, Value>, depth: usize) {
+    // `None` is left out, which is the default for every optional field.
+    for (key, value) in fields.iter().filter(|(_, v)| !v.is_null()) {
+        out.push_str(&INDENT.repeat(depth));
+        out.push_str(key);
+        out.push_str(" = ");
+        ycf_value(out, value, depth);
+        out.push('\n');
+    }
+}
+
+fn ycf_value(out: &mut String, value: &Value, depth: usize) {
+    match value {
+        Value::Null => unreachable!("null fields are skipped"),
+        Value::Bool(v) => out.push_str(&v.to_string()),
+        Value::Number(v) => out.push_str(&v.to_string()),
+        Value::String(v) => {
+            out.push('"');
+            for c in v.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+        // lists of plain values stay on one line, like `[[10000 20000] [15000 25000]]`.
+        Value::Array(items) if !items.iter().any(has_object) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                ycf_value(out, item, depth);
+            }
+            out.push(']');
+        }
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for item in items {
+                out.push_str(&INDENT.repeat(depth + 1));
+                ycf_value(out, item, depth + 1);
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            out.push_str("{\n");
+            ycf_fields(out, fields, depth + 1);
+            out.push_str(&INDENT.repeat(depth));
+            out.push('}');
+        }
+    }
+}
+
+fn has_object(value: &Value) -> bool {
+    match value {
+        Value::Object(_) => true,
+        Value::Array(items) => items.iter().any(has_object),
+        _ => false,
+    }
+}
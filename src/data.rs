@@ -5,12 +5,133 @@ use xivc_core::{enums::Job, math::{PlayerStats, WeaponInfo, PlayerInfo}};
 pub struct SimData {
     pub players: Vec<PlayerData>,
     pub enemies: Vec<EnemyData>,
+    // the timestamp when combat starts. ignored if `countdown` is set.
+    #[serde(default)]
     pub in_combat: u32,
+    // the length of the pull timer. the simulation starts when the countdown does.
+    #[serde(default)]
+    pub countdown: Option<u32>,
+    // start combat when the first hit lands on an enemy, instead of when the countdown ends.
+    #[serde(default)]
+    pub pull_on_hit: bool,
     pub end: u32,
+    // how many times to run the simulation.
+    #[serde(default = "default_runs")]
+    pub runs: u32,
+    // the seed of the first run. every following run uses the next seed.
+    // a random seed is picked if this is not set. toml can't hold seeds above
+    // 9223372036854775807, so no config can.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // replace critical hit, direct hit and damage variance rolls with their average.
+    // procs are still rolled.
+    #[serde(default)]
+    pub expected_damage: bool,
+    // how long before a hard cast snapshots a player can start moving without interrupting it.
+    #[serde(default = "default_slidecast")]
+    pub slidecast: u32,
+    // when the server mp tick happens, overriding `first_mp_tick` for every player.
+    #[serde(default)]
+    pub mp_tick_phase: Option<u32>,
+    // statuses that restore extra mp on every mp tick, like lucid dreaming.
+    #[serde(default)]
+    pub mp_regen: Vec<MpRegen>,
+    // in batch runs, pick a random server tick phase for every enemy instead of `first_actor_tick`.
+    #[serde(default)]
+    pub random_tick_phase: bool,
+    // the rest of the party, for jobs that aren't simulated.
+    #[serde(default)]
+    pub party: PartyConfig,
+    // how each kind of random roll is made.
+    #[serde(default)]
+    pub rng: RngConfig,
+    // how far each stat is moved up and down when finding stat weights.
+    #[serde(default = "default_weight_steps")]
+    pub weight_steps: StatDelta,
+    #[serde(default)]
+    pub sks_sweep: SweepConfig,
     #[serde(default)]
     pub report: ReportConfig,
 }
 
+impl SimData {
+    /// When combat is planned to start, which is the end of the countdown if there is one.
+    pub fn pull(&self) -> u32 {
+        self.countdown.unwrap_or(self.in_combat)
+    }
+}
+
+fn default_runs() -> u32 {
+    1
+}
+
+fn default_slidecast() -> u32 {
+    500
+}
+
+fn default_weight_steps() -> StatDelta {
+    StatDelta {
+        main: 50,
+        crt: 100,
+        det: 100,
+        dh: 100,
+        sks: 100,
+        wd: 3,
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RngConfig {
+    pub crit: RollStrategy,
+    pub dhit: RollStrategy,
+    pub variance: RollStrategy,
+    // any other roll made by a job, like silken symmetry or flow.
+    pub proc: RollStrategy,
+    // the esprit gained from the simulated dance partner.
+    pub partner: RollStrategy,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum RollStrategy {
+    #[default]
+    Random,
+    // the lowest possible roll, so anything that can happen will.
+    Always,
+    // the highest possible roll, so only things that are certain happen.
+    Never,
+    // the middle of the range. this is the average damage variance.
+    Average,
+    // read rolls from a file, going back to random rolls once they run out.
+    // each roll is `hit`, `miss`, or a number from 0 to 1 for where it should land in the range.
+    Sequence(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MpRegen {
+    pub status: String,
+    pub mp: u16,
+}
+
+// the skill speed values to run each player at.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SweepConfig {
+    pub from: u16,
+    pub to: u16,
+    pub step: u16,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            from: 420,
+            to: 1500,
+            step: 10,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ReportConfig {
@@ -21,6 +142,17 @@ pub struct ReportConfig {
     pub cast_snap: bool,
     pub job_event: bool,
     pub target: bool,
+    pub interrupt: bool,
+    // print the esprit gained from the simulated dance partner.
+    pub esprit: bool,
+    // print a per-player rotation summary after the simulation finishes.
+    pub rotation: bool,
+    // print the damage each player dealt by target, category and source.
+    pub breakdown: bool,
+    // print how each player's mp changed over the fight.
+    pub mp_timeline: bool,
+    // the names of the statuses to analyze buff alignment for, across all runs.
+    pub buffs: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -43,6 +175,246 @@ pub struct PlayerData {
     pub first_action: u32,
     #[serde(default)]
     pub actions: Vec<ActionKind<String>>,
+    // actions to use at an offset from the pull, which is negative before it.
+    // `tincture` drinks the player's tincture instead of using an action.
+    #[serde(default)]
+    pub prepull: Vec<(i32, String)>,
+    #[serde(default)]
+    pub consumables: Consumables,
+    // the periods in time when this player has to move, which interrupts hard casts.
+    #[serde(default)]
+    pub movement: Vec<(u32, u32)>,
+    // the periods in time when this player stops auto attacking.
+    #[serde(default)]
+    pub disengage: Vec<(u32, u32)>,
+    // alternative gear to compare against the stats above.
+    #[serde(default)]
+    pub gearsets: Vec<GearSet>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GearSet {
+    pub name: String,
+    // replaces the player's stats entirely.
+    #[serde(default)]
+    pub stats: Option<StatBlock>,
+    // replaces the player's weapon.
+    #[serde(default)]
+    pub weapon: Option<WeaponInfo>,
+    // added on top of the stats, after they are replaced.
+    #[serde(default)]
+    pub delta: StatDelta,
+}
+
+impl GearSet {
+    pub fn apply(&self, player: &mut PlayerData) {
+        if let Some(stats) = &self.stats {
+            player.stats = stats.0.clone();
+        }
+        if let Some(weapon) = &self.weapon {
+            player.weapon = weapon.clone();
+        }
+        self.delta.apply(&mut player.stats, &mut player.weapon);
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatBlock(#[serde(with = "StatData")] pub PlayerStats);
+
+// a change in stats. negative values lower the stat.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatDelta {
+    // the main stat. this is added to every attack stat, as only the one for the job is used.
+    pub main: i32,
+    pub crt: i32,
+    pub det: i32,
+    pub dh: i32,
+    pub sks: i32,
+    pub wd: i32,
+}
+
+impl StatDelta {
+    pub fn scale(self, by: i32) -> Self {
+        Self {
+            main: self.main * by,
+            crt: self.crt * by,
+            det: self.det * by,
+            dh: self.dh * by,
+            sks: self.sks * by,
+            wd: self.wd * by,
+        }
+    }
+
+    pub fn apply(&self, stats: &mut PlayerStats, weapon: &mut WeaponInfo) {
+        fn add(stat: u16, delta: i32) -> u16 {
+            (stat as i32 + delta).clamp(0, u16::MAX as i32) as u16
+        }
+
+        stats.str = add(stats.str, self.main);
+        stats.dex = add(stats.dex, self.main);
+        stats.int = add(stats.int, self.main);
+        stats.mnd = add(stats.mnd, self.main);
+        stats.crt = add(stats.crt, self.crt);
+        stats.det = add(stats.det, self.det);
+        stats.dh = add(stats.dh, self.dh);
+        stats.sks = add(stats.sks, self.sks);
+        weapon.wd = (weapon.wd as i32 + self.wd).max(0) as _;
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Consumables {
+    // the bonuses of the food the player has eaten, which last the whole fight.
+    pub food: Vec<FoodBonus>,
+    pub tincture: Option<Tincture>,
+}
+
+// raises a stat by a percent of itself, up to a cap.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FoodBonus {
+    pub stat: FoodStat,
+    pub percent: u16,
+    pub cap: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoodStat {
+    Vit,
+    Crt,
+    Det,
+    Dh,
+    Sks,
+    Sps,
+    Ten,
+    Pie,
+}
+
+// raises the main stat by a percent of itself, up to a cap, while it is active.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Tincture {
+    #[serde(default = "default_tincture_percent")]
+    pub percent: u16,
+    pub cap: u16,
+    #[serde(default = "default_tincture_duration")]
+    pub duration: u32,
+    #[serde(default = "default_tincture_cooldown")]
+    pub cooldown: u32,
+}
+
+fn default_tincture_percent() -> u16 {
+    10
+}
+
+fn default_tincture_duration() -> u32 {
+    30000
+}
+
+fn default_tincture_cooldown() -> u32 {
+    270000
+}
+
+fn bonus(stat: u16, percent: u16, cap: u16) -> u16 {
+    let bonus = (stat as u32 * percent as u32 / 100).min(cap as u32);
+    (stat as u32 + bonus).min(u16::MAX as u32) as u16
+}
+
+impl Consumables {
+    /// Adds the food bonuses to `stats`.
+    pub fn apply_food(&self, stats: &mut PlayerStats) {
+        for food in &self.food {
+            let stat = match food.stat {
+                FoodStat::Vit => &mut stats.vit,
+                FoodStat::Crt => &mut stats.crt,
+                FoodStat::Det => &mut stats.det,
+                FoodStat::Dh => &mut stats.dh,
+                FoodStat::Sks => &mut stats.sks,
+                FoodStat::Sps => &mut stats.sps,
+                FoodStat::Ten => &mut stats.ten,
+                FoodStat::Pie => &mut stats.pie,
+            };
+            *stat = bonus(*stat, food.percent, food.cap);
+        }
+    }
+}
+
+impl Tincture {
+    /// Adds the tincture to every main stat, as only the one for the job is used.
+    pub fn apply(&self, stats: &mut PlayerStats) {
+        for stat in [
+            &mut stats.str,
+            &mut stats.dex,
+            &mut stats.int,
+            &mut stats.mnd,
+        ] {
+            *stat = bonus(*stat, self.percent, self.cap);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartyConfig {
+    // the role of every party member, including the simulated players.
+    pub roles: Vec<Role>,
+    // raid buffs from party members that aren't simulated, applied to every player.
+    pub buffs: Vec<BuffStub>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    Tank,
+    Healer,
+    Melee,
+    PhysRanged,
+    Caster,
+}
+
+// a raid buff that is used at fixed times.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuffStub {
+    pub name: String,
+    // the first use, relative to the pull.
+    #[serde(default)]
+    pub start: i32,
+    // the time between uses. the buff is only used once if this isn't set.
+    #[serde(default)]
+    pub every: Option<u32>,
+    pub duration: u32,
+    // the percent added to the critical hit and direct hit chance.
+    #[serde(default)]
+    pub crit: u16,
+    #[serde(default)]
+    pub dhit: u16,
+    // the percent increase to damage dealt.
+    #[serde(default)]
+    pub damage: u16,
+}
+
+impl PartyConfig {
+    /// The percent added to the main stat, which is 1% for every different role.
+    pub fn bonus(&self) -> u16 {
+        let mut roles = self.roles.clone();
+        roles.sort_by_key(|v| *v as u8);
+        roles.dedup();
+        roles.len() as u16
+    }
+
+    /// Adds the party bonus to every main stat, as only the one for the job is used.
+    pub fn apply(&self, stats: &mut PlayerStats) {
+        let bonus = self.bonus() as u32;
+        for stat in [
+            &mut stats.str,
+            &mut stats.dex,
+            &mut stats.int,
+            &mut stats.mnd,
+        ] {
+            *stat = (*stat as u32 * (100 + bonus) / 100).min(u16::MAX as u32) as u16;
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
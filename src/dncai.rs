@@ -1,26 +1,26 @@
 #![allow(overlapping_range_endpoints)]
 
 use xivc_core::{
-    job::{
-        dnc::{
-            DncAction, DncState, Step, StepGauge, DANCE_OF_THE_DAWN_READY, FAN_DANCE_3,
-            FAN_DANCE_4, FINISHING_MOVE_READY, FLOURISH_FINISH, FLOURISH_FLOW, FLOURISH_SYMM,
-            LAST_DANCE_READY, SILKEN_FLOW, SILKEN_SYMM, STARFALL,
-        },
-        JobAction, State,
+    job::dnc::{
+        DncAction, DncState, Step, StepGauge, DANCE_OF_THE_DAWN_READY, FAN_DANCE_3, FAN_DANCE_4,
+        FINISHING_MOVE_READY, FLOURISH_FINISH, FLOURISH_FLOW, FLOURISH_SYMM, LAST_DANCE_READY,
+        SILKEN_FLOW, SILKEN_SYMM, STARFALL,
     },
     world::{
         queue::RadixEventQueue,
         status::{StatusEffect, StatusEvent, StatusEventKind},
-        ActorId, ActorRef, Event,
+        ActorId, Event,
     },
 };
 
 use crate::{
-    jobai::{Controller, JobAiCoro, ResumeCtx},
-    ActorHandle, JobAi, PlayerState, SimEvent,
+    jobai::{Controller, JobAiCoro},
+    ActorHandle, JobAi, SimEvent,
 };
 
+// how long before the pull the opener's standard finish is used.
+const STANDARD_FINISH_PREPULL: i64 = 2000;
+
 pub struct DncAi {
     _id: ActorId,
     coro: JobAiCoro,
@@ -35,29 +35,11 @@ impl DncAi {
     }
 }
 
-fn state(c: &Controller<'_>) -> DncState {
-    let lock = c.ctx().actor.actor.player.as_ref().unwrap().state.borrow();
-    let State::Dnc(state) = lock.clone() else {
-        panic!()
-    };
-    state
-}
-
-fn player<'w>(c: &'w Controller<'w>) -> &'w PlayerState {
-    c.ctx().actor.actor.player.as_ref().unwrap()
-}
-
-fn cooldown(c: &Controller, action: DncAction) -> u32 {
-    let (g, cd, ch) = action.cd_info().unwrap();
-
-    player(c).cooldowns.get(g.into()).unwrap().cd_until(cd, ch)
-}
-
-async fn cast(c: &mut Controller<'_>, action: DncAction) {
+async fn cast(c: &mut Controller, action: DncAction) {
     // analyze warnings.
     use DncAction::*;
     c.wait_lock().await;
-    let state = state(c);
+    let state = c.gauge::<DncState>();
     match action {
         FanDance | FanDance2 | Flourish if has_status(c, FAN_DANCE_3) => {
             eprintln!("[warn] fan dance 3 potentially overwritten.");
@@ -98,11 +80,13 @@ async fn cast(c: &mut Controller<'_>, action: DncAction) {
         _ => (),
     }
 
-    c.cast(action.into()).await;
+    if let Err(failure) = c.cast(action.into()).await {
+        eprintln!("[warn] {:?} failed: {:?}", action, failure);
+    }
 }
 
-async fn correct_step(c: &mut Controller<'_>) {
-    let state = state(c);
+async fn correct_step(c: &mut Controller) {
+    let state = c.gauge::<DncState>();
     let next = match state.step {
         StepGauge::Std { steps, completed } => {
             if let Some(v) = steps.get(completed as usize) {
@@ -132,20 +116,20 @@ async fn correct_step(c: &mut Controller<'_>) {
     cast(c, step).await;
 }
 
-async fn saber_dance(c: &mut Controller<'_>) {
-    if c.ctx().actor.has_own_status(DANCE_OF_THE_DAWN_READY) {
+async fn saber_dance(c: &mut Controller) {
+    if has_status(c, DANCE_OF_THE_DAWN_READY) {
         cast(c, DncAction::DanceOfTheDawn).await;
     } else {
         cast(c, DncAction::SaberDance).await;
     }
 }
 
-async fn next_feather(c: &mut Controller<'_>) -> bool {
+async fn next_feather(c: &mut Controller) -> bool {
     c.wait_lock().await;
     if has_status(c, FAN_DANCE_3) {
         cast(c, DncAction::FanDance3).await;
         true
-    } else if state(c).feathers > 0 {
+    } else if c.gauge::<DncState>().feathers > 0 {
         cast(c, DncAction::FanDance).await;
         true
     } else if has_status(c, FAN_DANCE_4) {
@@ -156,7 +140,7 @@ async fn next_feather(c: &mut Controller<'_>) -> bool {
     }
 }
 
-async fn burst_prio_combo(c: &mut Controller<'_>) {
+async fn burst_prio_combo(c: &mut Controller) {
     // TODO: Get buff duration priorities to work?
     // may not be nescessary when going in tech correctly.
     c.wait_gcd().await;
@@ -164,7 +148,11 @@ async fn burst_prio_combo(c: &mut Controller<'_>) {
         cast(c, DncAction::Fountainfall).await;
     } else if has_status(c, FLOURISH_SYMM) || has_status(c, SILKEN_SYMM) {
         cast(c, DncAction::ReverseCascade).await;
-    } else if state(c).combos.check_main_for(DncAction::Fountain) {
+    } else if c
+        .gauge::<DncState>()
+        .combos
+        .check_main_for(DncAction::Fountain)
+    {
         cast(c, DncAction::Fountain).await;
     } else {
         cast(c, DncAction::Cascade).await;
@@ -173,44 +161,44 @@ async fn burst_prio_combo(c: &mut Controller<'_>) {
 
 // starts at the first weave slot before the next gcd.
 // this is so feathers can be pooled correctly.
-async fn filler_prio(c: &mut Controller<'_>) {
+async fn filler_prio(c: &mut Controller) {
     use DncAction::*;
     c.wait_lock().await;
     let (action, genfeather) = if has_status(c, LAST_DANCE_READY) {
         (LastDance, false)
-    } else if state(c).esprit >= 70 {
+    } else if c.gauge::<DncState>().esprit >= 70 {
         (SaberDance, false)
     } else if has_status(c, FLOURISH_FLOW) || has_status(c, SILKEN_FLOW) {
         (Fountainfall, true)
     } else if has_status(c, FLOURISH_SYMM) || has_status(c, SILKEN_SYMM) {
         (ReverseCascade, true)
-    } else if state(c).combos.check_main_for(Fountain) {
+    } else if c.gauge::<DncState>().combos.check_main_for(Fountain) {
         (Fountain, false)
     } else {
         (Cascade, false)
     };
 
-    let flourish = cooldown(c, Flourish);
+    let flourish = c.cooldown_remaining(Flourish.into());
 
-    let feather_limit = if flourish < cooldown(c, Devilment) {
+    let feather_limit = if flourish < c.cooldown_remaining(Devilment.into()) {
         3
     } else {
         4
     };
 
-    if flourish <= (player(c).gcd as u32 - 650) {
+    if flourish <= (c.gcd_remaining() as u32 - 650) {
         if has_status(c, FAN_DANCE_3) {
             cast(c, FanDance3).await;
         }
         cast(c, Flourish).await;
-    } else if genfeather && state(c).feathers == feather_limit {
+    } else if genfeather && c.gauge::<DncState>().feathers == feather_limit {
         if has_status(c, FAN_DANCE_3) {
             cast(c, FanDance3).await;
             cast(c, FanDance).await;
         } else {
             cast(c, FanDance).await;
             c.wait_lock().await;
-            if player(c).gcd >= 650 {
+            if c.gcd_remaining() >= 650 {
                 if has_status(c, FAN_DANCE_3) {
                     cast(c, FanDance3).await;
                 } else if has_status(c, FAN_DANCE_4) {
@@ -227,14 +215,14 @@ async fn filler_prio(c: &mut Controller<'_>) {
     cast(c, action).await;
 }
 
-async fn filler_standard(c: &mut Controller<'_>) {
+async fn filler_standard(c: &mut Controller) {
     if has_status(c, FAN_DANCE_4) {
         cast(c, DncAction::FanDance4).await;
     }
     if has_status(c, FAN_DANCE_3) {
         cast(c, DncAction::FanDance3).await;
     }
-    if c.ctx().actor.has_own_status(FINISHING_MOVE_READY) {
+    if has_status(c, FINISHING_MOVE_READY) {
         cast(c, DncAction::FinishingMove).await;
     } else {
         cast(c, DncAction::StandardStep).await;
@@ -244,23 +232,23 @@ async fn filler_standard(c: &mut Controller<'_>) {
     }
 }
 
-async fn pretech_prio(c: &mut Controller<'_>) {
+async fn pretech_prio(c: &mut Controller) {
     use DncAction::*;
     c.wait_lock().await;
 
-    let (action, genfeather) = if state(c).esprit >= 50 {
+    let (action, genfeather) = if c.gauge::<DncState>().esprit >= 50 {
         (SaberDance, false)
     } else if has_status(c, FLOURISH_FLOW) || has_status(c, SILKEN_FLOW) {
         (Fountainfall, true)
     } else if has_status(c, FLOURISH_SYMM) || has_status(c, SILKEN_SYMM) {
         (ReverseCascade, true)
-    } else if state(c).combos.check_main_for(Fountain) {
+    } else if c.gauge::<DncState>().combos.check_main_for(Fountain) {
         (Fountain, false)
     } else {
         (Cascade, false)
     };
 
-    if genfeather && state(c).feathers == 4 {
+    if genfeather && c.gauge::<DncState>().feathers == 4 {
         if has_status(c, FAN_DANCE_3) {
             cast(c, FanDance3).await;
         }
@@ -271,26 +259,30 @@ async fn pretech_prio(c: &mut Controller<'_>) {
 }
 
 fn has_status(c: &Controller, status: StatusEffect) -> bool {
-    c.ctx().actor.has_own_status(status)
+    c.status_remaining(status, c.ctx().id) > 0
 }
 
 fn burst_state(c: &Controller) -> (u8, bool, bool, bool) {
     (
-        state(c).esprit.value(),
+        c.gauge::<DncState>().esprit.value(),
         has_status(c, LAST_DANCE_READY),
         has_status(c, STARFALL),
         has_status(c, FLOURISH_FINISH),
     )
 }
 
-async fn feather_weaves(c: &mut Controller<'_>) {
+async fn feather_weaves(c: &mut Controller) {
     if next_feather(c).await {
         next_feather(c).await;
     }
 }
 
-async fn burst(c: &mut Controller<'_>) {
+async fn burst(c: &mut Controller) {
     use DncAction::*;
+    // drunk right before technical step, so it covers the whole burst.
+    if c.potion_ready() {
+        c.use_potion().await;
+    }
     cast(c, DncAction::TechnicalStep).await;
     correct_step(c).await;
     correct_step(c).await;
@@ -298,12 +290,26 @@ async fn burst(c: &mut Controller<'_>) {
     correct_step(c).await;
     cast(c, DncAction::TechnicalFinish).await;
     cast(c, Devilment).await;
-    
+
     eprintln!("pool status:");
-    eprintln!("    {} feathers", state(c).feathers.value());
-    eprintln!("    {} fan dance 3", if has_status(c, FAN_DANCE_3) { "yes" } else { "no" });
-    eprintln!("    {} esprit", state(c).esprit.value());
-    eprintln!("    {} last dance", if has_status(c, LAST_DANCE_READY) { "yes" } else { "no" });
+    eprintln!("    {} feathers", c.gauge::<DncState>().feathers.value());
+    eprintln!(
+        "    {} fan dance 3",
+        if has_status(c, FAN_DANCE_3) {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    eprintln!("    {} esprit", c.gauge::<DncState>().esprit.value());
+    eprintln!(
+        "    {} last dance",
+        if has_status(c, LAST_DANCE_READY) {
+            "yes"
+        } else {
+            "no"
+        }
+    );
 
     // first gcd
     c.wait_gcd().await;
@@ -321,7 +327,7 @@ async fn burst(c: &mut Controller<'_>) {
         cast(c, Flourish).await;
     } else {
         cast(c, Flourish).await;
-        if player(c).gcd >= 650 {
+        if c.gcd_remaining() >= 650 {
             next_feather(c).await;
         }
     }
@@ -404,14 +410,14 @@ async fn burst(c: &mut Controller<'_>) {
     next_feather(c).await;
 }
 
-async fn coroutine(mut c: Controller<'_>) {
+async fn coroutine(mut c: Controller) {
     let c = &mut c;
     use DncAction::*;
     // Opener
     cast(c, StandardStep).await;
     correct_step(c).await;
     correct_step(c).await;
-    c.wait(12000).await;
+    c.wait_until_combat(-STANDARD_FINISH_PREPULL).await;
     cast(c, StandardFinish).await;
     // Start of loop
     #[allow(clippy::never_loop)]
@@ -421,13 +427,13 @@ async fn coroutine(mut c: Controller<'_>) {
         burst(c).await;
 
         for _ in 0..3 {
-            while cooldown(c, StandardStep) - player(c).gcd as u32 > 1000 {
+            while c.cooldown_remaining(StandardStep.into()) - c.gcd_remaining() as u32 > 1000 {
                 filler_prio(c).await;
             }
             filler_standard(c).await;
         }
 
-        while cooldown(c, TechnicalStep) - player(c).gcd as u32 > 1000 {
+        while c.cooldown_remaining(TechnicalStep.into()) - c.gcd_remaining() as u32 > 1000 {
             pretech_prio(c).await;
         }
     }
@@ -472,13 +478,6 @@ impl JobAi for DncAi {
             }
         }
 
-        self.coro
-            .resume(ResumeCtx {
-                event,
-                queue,
-                actor,
-                time,
-            })
-            .is_none()
+        self.coro.next(event, queue, actor, time)
     }
 }
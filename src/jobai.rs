@@ -1,175 +1,210 @@
 use std::{
-    cell::{Cell, UnsafeCell},
+    cell::RefCell,
     future::Future,
-    marker::{PhantomData, PhantomPinned},
-    mem::{self, MaybeUninit},
-    ops::AsyncFnOnce,
     pin::Pin,
+    rc::Rc,
     task::{Context, Poll, Waker},
 };
 
 use xivc_core::{
-    job::{self},
-    world::{queue::RadixEventQueue, Action},
+    job::{self, brd::BrdState, dnc::DncState, rpr::RprState, sam::SamState, CdGroup, State},
+    world::{queue::RadixEventQueue, status::StatusEffect, Action, ActorId},
 };
 
-use crate::{ActorHandle, CdEndEvent, SimEvent};
+use crate::{
+    ActorHandle, CastBar, CastFailure, CdEndEvent, JobAi, PlayerState, SimEvent, StatusEntry,
+    WorldState,
+};
 
+/// Runs an async function as an ai, resuming it once for every event in the simulation.
+///
+/// The function is given a [`Controller`], and is suspended every time it waits on it.
 pub struct JobAiCoro {
-    future: Pin<Box<dyn CtrlFuture>>,
-}
-
-struct ControllerFuture<F: Future> {
-    value: UnsafeCell<Option<OpaqueResumeCtx>>,
-    borrowed: Cell<bool>,
-    future: Option<F>,
-    _pin: PhantomPinned,
+    shared: Rc<RefCell<Shared>>,
+    // the coroutine doesn't have a context to start with until it is first resumed.
+    start: Option<StartFn>,
+    future: Option<Pin<Box<dyn Future<Output = ()>>>>,
 }
 
-trait CtrlFuture {
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()>;
-
-    fn set_value(self: Pin<&mut Self>, val: OpaqueResumeCtx);
-}
-
-impl<F: Future<Output = ()>> CtrlFuture for ControllerFuture<F> {
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
-        unsafe {
-            let this = self.get_unchecked_mut();
-
-            if let Some(v) = this.future.as_mut() {
-                match Pin::new_unchecked(v).poll(cx) {
-                    Poll::Pending => {
-                        if this.borrowed.get() {
-                            panic!();
-                        }
-                        Poll::Pending
-                    }
-                    Poll::Ready(..) => {
-                        this.future = None;
-                        Poll::Ready(())
-                    }
-                }
-            } else {
-                Poll::Ready(())
-            }
-        }
-    }
-
-    fn set_value(self: Pin<&mut Self>, val: OpaqueResumeCtx) {
-        if self.borrowed.get() {
-            panic!();
-        }
+type StartFn = Box<dyn FnOnce(Controller) -> Pin<Box<dyn Future<Output = ()>>>>;
 
-        unsafe {
-            *self.value.get() = Some(val);
-            self.borrowed.set(true);
-        }
-    }
+// what is passed between the coroutine and whatever is resuming it.
+#[derive(Default)]
+struct Shared {
+    // the context for the next time the coroutine is resumed.
+    next: Option<ResumeCtx>,
+    // the events pushed by the coroutine since it was last resumed.
+    events: Vec<(u32, SimEvent)>,
 }
 
 impl JobAiCoro {
-    pub fn new<F>(f: F) -> Self
+    pub fn new<F, Fut>(f: F) -> Self
     where
-        F: for<'c> AsyncFnOnce(Controller<'c>) + 'static,
+        F: FnOnce(Controller) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
     {
-        let mut future = Box::pin(ControllerFuture {
-            value: UnsafeCell::new(None),
-            borrowed: Cell::new(false),
+        Self {
+            shared: Rc::default(),
+            start: Some(Box::new(move |c| Box::pin(f(c)))),
             future: None,
-            _pin: PhantomPinned,
-        });
-
-        let value = &future.value as *const _;
-        let borrowed = &future.borrowed as *const _;
+        }
+    }
 
-        let controller = Controller {
-            borrowed,
-            value,
-            phantom: PhantomData,
+    /// Resumes the coroutine with the next event, returning `None` once it has finished.
+    pub fn resume(
+        &mut self,
+        event: &SimEvent,
+        queue: &mut RadixEventQueue<SimEvent>,
+        actor: ActorHandle,
+        time: u32,
+    ) -> Option<()> {
+        let ctx = ResumeCtx {
+            time,
+            event: event.clone(),
+            id: actor.id,
+            world: Rc::new(actor.world.clone()),
         };
 
-        let inner = f(controller);
-
-        unsafe {
-            future.as_mut().get_unchecked_mut().future = Some(inner);
+        if let Some(start) = self.start.take() {
+            self.future = Some(start(Controller {
+                shared: self.shared.clone(),
+                ctx,
+            }));
+        } else {
+            self.shared.borrow_mut().next = Some(ctx);
         }
+        let future = self.future.as_mut()?;
 
-        Self { future }
-    }
-
-    pub fn resume(&mut self, ctx: ResumeCtx<'_>) -> Option<()> {
         let mut cx = Context::from_waker(Waker::noop());
+        let poll = future.as_mut().poll(&mut cx);
 
-        let opaque_ctx: OpaqueResumeCtx = unsafe { mem::transmute(ctx) };
-        self.future.as_mut().set_value(opaque_ctx);
-        match self.future.as_mut().poll(&mut cx) {
-            Poll::Ready(_) => None,
-            Poll::Pending => Some(()),
+        let mut shared = self.shared.borrow_mut();
+        for (time, event) in shared.events.drain(..) {
+            queue.push(time, event);
+        }
+
+        match poll {
+            Poll::Ready(()) => {
+                self.future = None;
+                None
+            }
+            Poll::Pending => {
+                // the context is only taken by `Controller::yield_wait`, so if it is still here
+                // the coroutine is waiting on something that will never be woken up.
+                if shared.next.is_some() {
+                    panic!("an ai coroutine waited on something other than its controller");
+                }
+                Some(())
+            }
         }
     }
 }
 
-#[repr(C)]
-pub struct ResumeCtx<'w> {
-    pub time: u32,
-    pub event: &'w SimEvent,
-    pub queue: &'w mut RadixEventQueue<SimEvent>,
-    pub actor: ActorHandle<'w>,
+// lets a coroutine be used as an ai on its own, for ones that don't need anything else.
+impl JobAi for JobAiCoro {
+    fn next(
+        &mut self,
+        event: &SimEvent,
+        queue: &mut RadixEventQueue<SimEvent>,
+        actor: ActorHandle,
+        time: u32,
+    ) -> bool {
+        self.resume(event, queue, actor, time).is_none()
+    }
 }
 
-#[repr(C)]
-struct OpaqueResumeCtx(MaybeUninit<ResumeCtx<'static>>);
+/// A snapshot of the simulation from when a coroutine was last resumed.
+///
+/// The world is cloned for every resume, so the coroutine owns everything it can see
+/// and nothing it holds on to across a wait can be changed under it.
+#[derive(Clone, Debug)]
+pub struct ResumeCtx {
+    pub time: u32,
+    pub event: SimEvent,
+    // the actor being controlled.
+    pub id: ActorId,
+    world: Rc<WorldState>,
+}
 
-impl OpaqueResumeCtx {
-    fn as_resume_ctx_mut(&mut self) -> &mut ResumeCtx {
-        unsafe { mem::transmute(self) }
+impl ResumeCtx {
+    pub fn world(&self) -> &WorldState {
+        &self.world
     }
-    fn as_resume_ctx_ref(&self) -> &ResumeCtx {
-        unsafe { mem::transmute(self) }
+
+    /// The actor being controlled.
+    pub fn actor(&self) -> ActorHandle<'_> {
+        ActorHandle {
+            actor: &self.world.actors[self.id.0 as usize],
+            id: self.id,
+            world: &self.world,
+        }
     }
 }
 
-pub struct Controller<'w> {
-    value: *const UnsafeCell<Option<OpaqueResumeCtx>>,
-    borrowed: *const Cell<bool>,
-    phantom: PhantomData<&'w ()>,
+/// A job gauge that can be read with [`Controller::gauge`].
+pub trait JobGauge: Clone {
+    fn from_state(state: &State) -> Option<&Self>;
 }
 
-impl<'w> Controller<'w> {
-    pub fn ctx_mut(&mut self) -> &mut ResumeCtx {
-        unsafe {
-            assert!((*self.borrowed).get());
-            (*UnsafeCell::raw_get(self.value))
-                .as_mut()
-                .unwrap()
-                .as_resume_ctx_mut()
+impl JobGauge for BrdState {
+    fn from_state(state: &State) -> Option<&Self> {
+        match state {
+            State::Brd(v) => Some(v),
+            _ => None,
         }
     }
+}
 
-    pub fn ctx(&self) -> &ResumeCtx {
-        unsafe {
-            assert!((*self.borrowed).get());
-            (*UnsafeCell::raw_get(self.value))
-                .as_ref()
-                .unwrap()
-                .as_resume_ctx_ref()
+impl JobGauge for DncState {
+    fn from_state(state: &State) -> Option<&Self> {
+        match state {
+            State::Dnc(v) => Some(v),
+            _ => None,
         }
     }
 }
 
-impl<'w> Controller<'w> {
-    pub fn yield_wait(&mut self) -> impl Future<Output = ()> + '_ {
-        struct Impl {
-            finished: bool,
+impl JobGauge for RprState {
+    fn from_state(state: &State) -> Option<&Self> {
+        match state {
+            State::Rpr(v) => Some(v),
+            _ => None,
         }
+    }
+}
 
-        unsafe {
-            UnsafeCell::raw_get(self.value).replace(None);
-            (*self.borrowed).set(false);
+impl JobGauge for SamState {
+    fn from_state(state: &State) -> Option<&Self> {
+        match state {
+            State::Sam(v) => Some(v),
+            _ => None,
         }
+    }
+}
+
+/// The handle a coroutine uses to see the simulation and act in it.
+pub struct Controller {
+    shared: Rc<RefCell<Shared>>,
+    ctx: ResumeCtx,
+}
+
+impl Controller {
+    pub fn ctx(&self) -> &ResumeCtx {
+        &self.ctx
+    }
 
-        impl Future for Impl {
+    /// Pushes an event to the simulation's queue.
+    pub fn push(&mut self, time: u32, event: SimEvent) {
+        self.shared.borrow_mut().events.push((time, event));
+    }
+
+    /// Suspends the coroutine until the next event.
+    pub async fn yield_wait(&mut self) {
+        struct Yield {
+            finished: bool,
+        }
+
+        impl Future for Yield {
             type Output = ();
 
             fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
@@ -182,24 +217,166 @@ impl<'w> Controller<'w> {
             }
         }
 
-        Impl { finished: false }
+        Yield { finished: false }.await;
+
+        self.ctx = self
+            .shared
+            .borrow_mut()
+            .next
+            .take()
+            .expect("an ai coroutine was polled without being resumed");
+    }
+
+    /// The state of the controlled player.
+    ///
+    /// # Panics
+    /// If the controlled actor isn't a player.
+    pub fn player(&self) -> &PlayerState {
+        self.ctx
+            .actor()
+            .actor
+            .player
+            .as_ref()
+            .expect("an ai is controlling an actor that isn't a player")
+    }
+
+    /// A copy of the job gauge of the controlled player.
+    ///
+    /// # Panics
+    /// If the player isn't the job the gauge is for.
+    pub fn gauge<S: JobGauge>(&self) -> S {
+        S::from_state(&self.player().state.borrow())
+            .expect("the job gauge doesn't match the player's job")
+            .clone()
+    }
+
+    /// The time until `action` can be used again, ignoring the gcd.
+    ///
+    /// Actions the player's job doesn't have are never on cooldown.
+    pub fn cooldown_remaining(&self, action: job::Action) -> u32 {
+        action
+            .cd_info()
+            .and_then(|(g, cd, ch)| Some(self.player().cooldowns.get(g)?.cd_until(cd, ch)))
+            .unwrap_or_default()
+    }
+
+    /// The number of charges of `action` that can be used right now.
+    ///
+    /// Actions without a cooldown always have a single charge,
+    /// and actions the player's job doesn't have have none.
+    pub fn charges(&self, action: job::Action) -> u8 {
+        let Some((g, cd, ch)) = action.cd_info() else {
+            return 1;
+        };
+        let Some(cooldown) = self.player().cooldowns.get(g) else {
+            return 0;
+        };
+        // with a single charge, the time until it is ready is the time until
+        // every charge is back, which is a whole cooldown for each missing charge.
+        let full = cooldown.cd_until(cd, 1);
+        let missing = full.div_ceil((cd as u32).max(1));
+        (ch as u32).saturating_sub(missing) as u8
+    }
+
+    pub fn gcd_remaining(&self) -> u16 {
+        self.player().gcd
+    }
+
+    /// The time left on `effect` applied by the controlled actor onto `on`, or 0 if it isn't there.
+    pub fn status_remaining(&self, effect: StatusEffect, on: ActorId) -> u32 {
+        self.own_status(effect, on)
+            .map(|v| v.instance.time)
+            .unwrap_or_default()
+    }
+
+    /// If `on` has `effect`, applied by anyone.
+    pub fn has_status(&self, effect: StatusEffect, on: ActorId) -> bool {
+        self.ctx
+            .world
+            .actors
+            .get(on.0 as usize)
+            .is_some_and(|actor| actor.statuses.keys().any(|(_, v)| *v == effect))
+    }
+
+    /// The stacks of `effect` the controlled actor has on themselves, or 0 if it isn't there.
+    pub fn stacks(&self, effect: StatusEffect) -> u8 {
+        self.own_status(effect, self.ctx.id)
+            .map(|v| v.instance.stack)
+            .unwrap_or_default()
+    }
+
+    /// The time until a charge of anything in `group` can be used again.
+    pub fn group_cooldown_remaining(&self, group: CdGroup) -> u32 {
+        self.player()
+            .cd_ready
+            .iter()
+            .find(|v| v.0 == group)
+            .map(|v| v.1.saturating_sub(self.ctx.time))
+            .unwrap_or_default()
+    }
+
+    /// The cast bar the controlled player is in the middle of.
+    pub fn casting(&self) -> Option<CastBar> {
+        self.player().casting
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.player().moving
+    }
+
+    pub fn mp(&self) -> u16 {
+        self.player().mp
+    }
+
+    /// The mp `action` would cost right now, or `None` if it can't be cast at all.
+    pub fn mp_cost(&self, action: job::Action) -> Option<u16> {
+        let player = self.player();
+        let world = self.ctx.world();
+        player
+            .job
+            .check_cast(action, &player.state.borrow(), &world, self.ctx.actor())
+            .ok()
+            .map(|info| info.mp)
+    }
+
+    /// If the controlled player has enough mp to cast `action`.
+    pub fn can_afford(&self, action: job::Action) -> bool {
+        self.mp_cost(action).is_some_and(|cost| cost <= self.mp())
+    }
+
+    /// The time since combat started, which is negative before the pull.
+    pub fn time_in_combat(&self) -> i64 {
+        self.ctx.time as i64 - self.ctx.world.in_combat as i64
+    }
+
+    fn own_status(&self, effect: StatusEffect, on: ActorId) -> Option<&StatusEntry> {
+        let id = self.ctx.id;
+        let key = (if effect.unique { None } else { Some(id) }, effect);
+        self.ctx
+            .world
+            .actors
+            .get(on.0 as usize)?
+            .statuses
+            .get(&key)
+            .filter(|v| v.instance.source == id)
     }
 
     pub async fn wait_action(&mut self, action: job::Action) {
-        let player = self.ctx().actor.actor.player.as_ref().unwrap();
+        let player = self.player();
 
         let cd = action
             .cd_info()
-            .map(|(g, cd, ch)| (g, player.cooldowns.get(g).unwrap().cd_until(cd, ch)));
+            .and_then(|(g, cd, ch)| Some((g, player.cooldowns.get(g)?.cd_until(cd, ch))));
 
         let mut wait_gcd = action.gcd() && player.gcd > 0;
         let mut wait_lock = player.lock > 0;
         let mut wait_cd = cd.filter(|v| v.1 > 0).map(|v| v.0);
 
         loop {
-            match self.ctx().event {
-                SimEvent::CdEnd(CdEndEvent::Gcd) => wait_gcd = false,
-                SimEvent::CdEnd(CdEndEvent::Lock) => wait_lock = false,
+            match &self.ctx().event {
+                // these can be left over from a cast that was interrupted, so check they're real.
+                SimEvent::CdEnd(CdEndEvent::Gcd) => wait_gcd = self.player().gcd > 0,
+                SimEvent::CdEnd(CdEndEvent::Lock) => wait_lock = self.player().lock > 0,
                 SimEvent::CdEnd(CdEndEvent::JobCd(e)) if Some(*e) == wait_cd => wait_cd = None,
                 _ => (),
             }
@@ -211,15 +388,15 @@ impl<'w> Controller<'w> {
     }
 
     pub async fn wait_gcd(&mut self) {
-        let player = self.ctx().actor.actor.player.as_ref().unwrap();
+        let player = self.player();
 
         let mut wait_lock = player.lock > 0;
         let mut wait_gcd = player.gcd > 0;
 
         loop {
-            match self.ctx().event {
-                SimEvent::CdEnd(CdEndEvent::Gcd) => wait_gcd = false,
-                SimEvent::CdEnd(CdEndEvent::Lock) => wait_lock = false,
+            match &self.ctx().event {
+                SimEvent::CdEnd(CdEndEvent::Gcd) => wait_gcd = self.player().gcd > 0,
+                SimEvent::CdEnd(CdEndEvent::Lock) => wait_lock = self.player().lock > 0,
                 _ => (),
             }
             if !wait_lock && !wait_gcd {
@@ -230,13 +407,12 @@ impl<'w> Controller<'w> {
     }
 
     pub async fn wait_before_gcd(&mut self, before: u16) {
-        let ctx = self.ctx();
-        let player = ctx.actor.actor.player.as_ref().unwrap();
-        let time = ctx.time;
+        let player = self.player();
+        let time = self.ctx().time;
 
         let target = if player.gcd > before {
             let target = (player.gcd - before) as u32 + time;
-            self.ctx_mut().queue.push(target, SimEvent::Other);
+            self.push(target, SimEvent::Other);
             target
         } else {
             return;
@@ -253,13 +429,13 @@ impl<'w> Controller<'w> {
     }
 
     pub async fn wait_lock(&mut self) {
-        let player = self.ctx().actor.actor.player.as_ref().unwrap();
+        let player = self.player();
 
         let mut wait_lock = player.lock > 0;
 
         loop {
             if matches!(self.ctx().event, SimEvent::CdEnd(CdEndEvent::Lock)) {
-                wait_lock = false;
+                wait_lock = self.player().lock > 0;
             }
             if !wait_lock {
                 break;
@@ -268,30 +444,63 @@ impl<'w> Controller<'w> {
         }
     }
 
-    pub async fn cast(&mut self, action: job::Action) {
+    /// Waits until `action` can be used and casts it, returning once it snapshots.
+    ///
+    /// If the cast can't happen, this returns why as soon as it is known.
+    pub async fn cast(&mut self, action: job::Action) -> Result<(), CastFailure> {
         self.wait_action(action).await;
 
-        let ctx = self.ctx_mut();
-        let time = ctx.time;
-        let actor = ctx.actor.id;
-        ctx.queue
-            .push(time, SimEvent::StartCast(actor, Action::Job(action)));
+        let time = self.ctx().time;
+        let actor = self.ctx().id;
+        let action = Action::Job(action);
+        self.push(time, SimEvent::StartCast(actor, action));
 
         loop {
             self.yield_wait().await;
-            match self.ctx().event {
-                SimEvent::CastSnap(id, ac) if *id == actor && *ac == Action::Job(action) => return,
+            match &self.ctx().event {
+                SimEvent::CastSnap(id, ac) if *id == actor && *ac == action => return Ok(()),
+                SimEvent::CastFailed(id, ac, failure) if *id == actor && *ac == action => {
+                    return Err(failure.clone())
+                }
                 _ => (),
             }
         }
     }
 
-    pub async fn wait(&mut self, delay: u32) {
-        let ctx = self.ctx_mut();
+    /// If the controlled player has a tincture that is off cooldown, and isn't casting.
+    pub fn potion_ready(&self) -> bool {
+        let player = self.player();
+        player.tincture.is_some()
+            && player.tincture_ready <= self.ctx().time
+            && player.casting.is_none()
+    }
 
-        let target_time = ctx.time + delay;
+    /// Waits for the animation lock and drinks a tincture, returning if it was drunk.
+    ///
+    /// Nothing is used if the player has no tincture, it is on cooldown or a cast bar is running.
+    pub async fn use_potion(&mut self) -> bool {
+        self.wait_lock().await;
+        if !self.potion_ready() {
+            return false;
+        }
 
-        ctx.queue.push(target_time, SimEvent::Other);
+        let time = self.ctx().time;
+        let actor = self.ctx().id;
+        self.push(time, SimEvent::UsePotion(actor));
+
+        loop {
+            self.yield_wait().await;
+            if matches!(self.ctx().event, SimEvent::UsePotion(id) if id == actor) {
+                // drinking it puts the tincture on cooldown.
+                return self.player().tincture_ready > time;
+            }
+        }
+    }
+
+    pub async fn wait(&mut self, delay: u32) {
+        let target_time = self.ctx().time + delay;
+
+        self.push(target_time, SimEvent::Other);
 
         loop {
             self.yield_wait().await;
@@ -301,4 +510,116 @@ impl<'w> Controller<'w> {
             }
         }
     }
+
+    /// Waits until `predicate` is true, which is checked on every event.
+    ///
+    /// Returns `true` if `timeout` ran out first.
+    pub async fn wait_until(
+        &mut self,
+        predicate: impl FnMut(&Controller) -> bool + 'static,
+        timeout: Option<u32>,
+    ) -> bool {
+        self.wait_timeout(Wait::until(predicate), timeout).await
+    }
+
+    /// Waits until the controlled actor has `effect` on themselves, applied by anyone.
+    ///
+    /// Returns `true` if `timeout` ran out first.
+    pub async fn wait_for_status(&mut self, effect: StatusEffect, timeout: Option<u32>) -> bool {
+        self.wait_timeout(Wait::status(effect), timeout).await
+    }
+
+    /// Waits until a charge of anything in `group` can be used.
+    ///
+    /// Returns `true` if `timeout` ran out first.
+    pub async fn wait_for_cooldown(&mut self, group: CdGroup, timeout: Option<u32>) -> bool {
+        self.wait_timeout(Wait::cooldown(group), timeout).await
+    }
+
+    /// Waits until the simulation reaches `time`, returning right away if it already has.
+    pub async fn wait_until_time(&mut self, time: u32) {
+        self.wait_timeout(Wait::time(time), None).await;
+    }
+
+    /// Waits until `offset` from the pull, which is negative before it.
+    pub async fn wait_until_combat(&mut self, offset: i64) {
+        let time = self.ctx.world.in_combat as i64 + offset;
+        self.wait_until_time(time.max(0) as u32).await;
+    }
+
+    /// Waits until either `a` or `b` is ready, returning which one it was.
+    ///
+    /// If both are ready at the same time, `a` wins.
+    pub async fn select(&mut self, mut a: Wait, mut b: Wait) -> Selected {
+        a.start(self);
+        b.start(self);
+        loop {
+            if a.ready(self) {
+                return Selected::First;
+            }
+            if b.ready(self) {
+                return Selected::Second;
+            }
+            self.yield_wait().await;
+        }
+    }
+
+    async fn wait_timeout(&mut self, wait: Wait, timeout: Option<u32>) -> bool {
+        let timeout = match timeout {
+            Some(timeout) => Wait::time(self.ctx.time + timeout),
+            None => Wait::until(|_| false),
+        };
+        self.select(wait, timeout).await == Selected::Second
+    }
+}
+
+/// Something a coroutine can wait on with [`Controller::select`].
+pub enum Wait {
+    // until the function returns true.
+    Until(Box<dyn FnMut(&Controller) -> bool>),
+    // until the simulation reaches a time.
+    Time(u32),
+}
+
+impl Wait {
+    pub fn until(predicate: impl FnMut(&Controller) -> bool + 'static) -> Self {
+        Self::Until(Box::new(predicate))
+    }
+
+    pub fn time(time: u32) -> Self {
+        Self::Time(time)
+    }
+
+    /// Until the controlled actor has `effect` on themselves, applied by anyone.
+    pub fn status(effect: StatusEffect) -> Self {
+        Self::until(move |c| c.has_status(effect, c.ctx().id))
+    }
+
+    /// Until a charge of anything in `group` can be used.
+    pub fn cooldown(group: CdGroup) -> Self {
+        Self::until(move |c| c.group_cooldown_remaining(group) == 0)
+    }
+
+    fn start(&self, c: &mut Controller) {
+        // nothing else might happen at that time, so make sure there is an event to wake up on.
+        if let Self::Time(time) = *self {
+            if time > c.ctx().time {
+                c.push(time, SimEvent::Other);
+            }
+        }
+    }
+
+    fn ready(&mut self, c: &Controller) -> bool {
+        match self {
+            Self::Until(predicate) => predicate(c),
+            Self::Time(time) => c.ctx().time >= *time,
+        }
+    }
+}
+
+/// Which wait finished first in [`Controller::select`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selected {
+    First,
+    Second,
 }
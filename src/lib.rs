@@ -0,0 +1,2360 @@
+//! A text based simulator for xivc.
+//!
+//! A [`Simulation`] is built from [`SimData`] with [`Simulation::builder`], and players are
+//! controlled by a [`JobAi`]. Every report made during a run is kept in [`Simulation::log`].
+
+#![allow(clippy::collapsible_if)]
+#![allow(clippy::collapsible_else_if)]
+
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    iter::{self},
+    sync::Mutex,
+};
+
+use data::{BuffStub, MpRegen, ReportConfig, SimData, Tincture};
+use dncai::DncAi;
+use indexmap::{map, IndexMap};
+use rng::{ActorRng, SequenceError, SimRngSource};
+use serde::de::value::{Error, StrDeserializer};
+use xivc_core::{
+    enums::{DamageElement, DamageInstance, Job},
+    job::{
+        self,
+        dnc::{LAST_DANCE_READY, TECHNICAL_FINISH},
+        CastError, CdGroup, CdMap, DynJob, JobEvent, State,
+    },
+    math::{
+        Buffs, EotSnapshot, HitTypeHandle, PlayerInfo, PlayerStats, SpeedStat, WeaponInfo, XivMath,
+    },
+    timing::{ActionCd, DurationInfo, ScaleTime},
+    world::{
+        queue::RadixEventQueue,
+        status::{StatusEffect, StatusEvent, StatusEventKind, StatusInstance, StatusSnapshot},
+        Action, ActionTargetting, ActorId, ActorRef, CriticalHit, DamageEvent, DamageVariance,
+        DirectHit, Event, EventRng, EventSink, Faction, Positional, WorldRef,
+    },
+};
+
+pub mod actions;
+pub mod analysis;
+pub mod batch;
+pub mod config;
+pub mod data;
+pub mod dncai;
+pub mod jobai;
+pub mod rng;
+#[cfg(test)]
+mod tests;
+pub mod validate;
+
+#[derive(Debug)]
+pub struct Simulation {
+    pub world: WorldState,
+    events: RadixEventQueue<SimEvent>,
+    rng: SimRngSource,
+    // kept in the order of the players, so the ais always run in the same order.
+    actions: IndexMap<ActorId, DynJobAi>,
+    pub report: ReportConfig,
+    slidecast: u32,
+    mp_regen: Vec<MpRegen>,
+    // the length of the fight after the pull.
+    length: u32,
+    // every report that was made, in order. this is what the analysis passes read.
+    pub log: Vec<(u32, ReportKind)>,
+}
+
+/// Builds a [`Simulation`] from a config.
+pub struct SimulationBuilder {
+    data: SimData,
+    seed: u64,
+    // the ais to use instead of the default one for each player.
+    ais: HashMap<ActorId, DynJobAi>,
+}
+
+impl SimulationBuilder {
+    /// Sets the seed all of the random rolls are made from.
+    ///
+    /// This defaults to the `seed` in the config, or a random one if that isn't set.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Controls the player `actor` with `ai` instead of the default ai for their job.
+    ///
+    /// Players are numbered in the order they are in the config, starting from 0.
+    pub fn ai(mut self, actor: ActorId, ai: impl JobAi + 'static) -> Self {
+        self.ais.insert(actor, DynJobAi(Box::new(ai)));
+        self
+    }
+}
+
+struct DynJobAi(Box<dyn JobAi>);
+
+impl fmt::Debug for DynJobAi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynNextAction").finish()
+    }
+}
+
+/// The logic that decides what a player does.
+pub trait JobAi {
+    /// Called for every event in the simulation, with the player this ai controls.
+    ///
+    /// Any events the ai wants to happen, like casting an action, are pushed to `queue`.
+    /// Returns `true` once the ai is finished and shouldn't be called anymore.
+    fn next(
+        &mut self,
+        event: &SimEvent,
+        queue: &mut RadixEventQueue<SimEvent>,
+        actor: ActorHandle,
+        time: u32,
+    ) -> bool;
+}
+
+#[derive(Clone, Debug)]
+pub enum SimEvent {
+    Event(Event),
+    StartCast(ActorId, Action),
+    CastSnap(ActorId, Action),
+    // a cast that was started, but couldn't happen.
+    CastFailed(ActorId, Action, CastFailure),
+    // the end of a cast bar, which snapshots the cast if it wasn't interrupted.
+    CastBarEnd(ActorId, Action),
+    MoveStart(ActorId),
+    MoveEnd(ActorId),
+    Disengage(ActorId),
+    Engage(ActorId),
+    // a raid buff from the party config going up on every player for this long.
+    RaidBuff(usize, u32),
+    // drinks a tincture, if the player has one off cooldown.
+    UsePotion(ActorId),
+    Untargetable(ActorId),
+    Targetable(ActorId),
+    AutoAttack(ActorId),
+    CheckStatusFalloff,
+    CdEnd(CdEndEvent),
+    SimStart,
+    Other,
+    EspritIdk,
+}
+
+/// Why a cast couldn't happen.
+#[derive(Clone, Debug)]
+pub enum CastFailure {
+    /// The job rejected the cast, like when a status it needs is missing.
+    Rejected(CastError),
+    /// A cooldown group the action uses won't be ready for this long.
+    Cooldown(CdGroup, u32),
+    /// The player doesn't have enough mp.
+    Mp { cost: u16, mp: u16 },
+    /// The action has a cast bar, and the player is moving.
+    Moving,
+    /// The player is in the middle of another cast bar.
+    Casting,
+    /// The player started moving during the cast bar.
+    Interrupted,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CdEndEvent {
+    Lock,
+    Gcd,
+    JobCd(CdGroup),
+}
+
+#[derive(Debug)]
+pub enum FromSimDataError {
+    UnknownAction(Job, String),
+    RollSequence(SequenceError),
+}
+
+impl fmt::Display for FromSimDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAction(job, name) => write!(f, "unknown {:?} action `{}`", job, name),
+            Self::RollSequence(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FromSimDataError {}
+
+impl SimulationBuilder {
+    pub fn build(self) -> Result<Simulation, FromSimDataError> {
+        let Self {
+            data,
+            seed,
+            mut ais,
+        } = self;
+        let mut events = RadixEventQueue::new();
+        let mut actors = Vec::new();
+        let mut actions = IndexMap::new();
+        let pull = data.pull();
+        // players attack the first enemy, which comes right after them.
+        let first_enemy = ActorId(data.players.len() as u16);
+        for player in data.players {
+            let id = ActorId(actors.len() as u16);
+            let job = player.job;
+            // weapon delay is in hundredths of a second.
+            let auto_delay = player.weapon.delay as u32 * 10;
+            let mut stats = player.stats;
+            data.party.apply(&mut stats);
+            player.consumables.apply_food(&mut stats);
+            let state = ActorState {
+                name: player.name,
+                damage: 0,
+                dealt: DamageLedger::default(),
+                player: Some(PlayerState {
+                    job_kind: job,
+                    cooldowns: CdMap::default_for(job),
+                    cd_ready: Vec::new(),
+                    casting: None,
+                    moving: false,
+                    auto_delay,
+                    next_auto: Some(player.first_auto_attack),
+                    engaged: true,
+                    gcd: 0,
+                    job: DynJob::from_job(job),
+                    lock: 0,
+                    math: player_math(&stats, &player.weapon, &player.player_info, None),
+                    stats,
+                    weapon: player.weapon,
+                    info: player.player_info,
+                    tincture: player.consumables.tincture,
+                    tincture_ready: 0,
+                    mp: 10000,
+                    state: RefCell::new(State::default_for(job)),
+                }),
+                statuses: IndexMap::new(),
+                target: Some(first_enemy),
+                targetable: true,
+            };
+
+            events.push(
+                player.first_actor_tick,
+                SimEvent::Event(Event::ActorTick(id)),
+            );
+            events.push(
+                data.mp_tick_phase.unwrap_or(player.first_mp_tick),
+                SimEvent::Event(Event::MpTick(id)),
+            );
+            events.push(player.first_auto_attack, SimEvent::AutoAttack(id));
+            for (start, end) in player.movement {
+                events.push(start, SimEvent::MoveStart(id));
+                events.push(end, SimEvent::MoveEnd(id));
+            }
+            for (start, end) in player.disengage {
+                events.push(start, SimEvent::Disengage(id));
+                events.push(end, SimEvent::Engage(id));
+            }
+            for (offset, name) in player.prepull {
+                let time = (pull as i64 + offset as i64).max(0) as u32;
+                if name == "tincture" {
+                    events.push(time, SimEvent::UsePotion(id));
+                    continue;
+                }
+                let action =
+                    job::Action::deserialize_for(job, StrDeserializer::<Error>::new(&name))
+                        .map_err(|_| FromSimDataError::UnknownAction(job, name))?;
+                events.push(time, SimEvent::StartCast(id, Action::Job(action)));
+            }
+
+            // let mut acs = Vec::new();
+            // dumb hack
+            // for action in player.actions {
+            //     match action {
+            //         ActionKind::Normal(s) => {
+            //             acs.push(ActionKind::Normal(Action::Job(
+            //                 job::Action::deserialize_for(job, StrDeserializer::<Error>::new(&*s))
+            //                     .map_err(|_| FromSimDataError::UnknownAction(job, s))?,
+            //             )));
+            //         }
+            //         ActionKind::Delay(d, s) => {
+            //             acs.push(ActionKind::Delay(
+            //                 d,
+            //                 Action::Job(
+            //                     job::Action::deserialize_for(
+            //                         job,
+            //                         StrDeserializer::<Error>::new(&*s),
+            //                     )
+            //                     .map_err(|_| FromSimDataError::UnknownAction(job, s))?,
+            //                 ),
+            //             ));
+            //         }
+            //     }
+            // }
+
+            // let mut acs = acs.into_iter().peekable();
+
+            // then push the first one in the list to the event list.
+            // if let Some(ac) = acs.next() {
+            //     let (t, ac) = match ac {
+            //         ActionKind::Normal(ac) => (player.first_action, ac),
+            //         ActionKind::Delay(d, ac) => (player.first_action + d, ac),
+            //     };
+            //     events.push(t, SimEvent::StartCast(id, ac));
+            // }
+
+            if state.player.is_some() {
+                let acs = ais
+                    .remove(&id)
+                    .unwrap_or_else(|| DynJobAi(Box::new(DncAi::new(id))));
+
+                actions.insert(id, acs);
+            }
+
+            actors.push(state);
+        }
+        for enemy in data.enemies {
+            let id = ActorId(actors.len() as u16);
+            let state = ActorState {
+                name: enemy.name,
+                damage: 0,
+                dealt: DamageLedger::default(),
+                player: None,
+                statuses: IndexMap::new(),
+                target: None,
+                targetable: true,
+            };
+            events.push(
+                enemy.first_actor_tick,
+                SimEvent::Event(Event::ActorTick(id)),
+            );
+            actors.push(state);
+            for (start, end) in enemy.untarget {
+                events.push(start, SimEvent::Untargetable(id));
+                events.push(end, SimEvent::Targetable(id));
+            }
+        }
+
+        // with `pull_on_hit`, the pull isn't known until the first hit lands.
+        if !data.pull_on_hit {
+            schedule_raid_buffs(&mut events, &data.party.buffs, pull, 0, data.end);
+        }
+
+        events.push(0, SimEvent::EspritIdk);
+        events.push(0, SimEvent::SimStart);
+
+        Ok(Simulation {
+            events,
+            world: WorldState {
+                time: 0,
+                in_combat: pull,
+                pull_on_hit: data.pull_on_hit,
+                raid_buffs: data
+                    .party
+                    .buffs
+                    .into_iter()
+                    .map(|stub| RaidBuffState {
+                        status: StatusEffect {
+                            name: static_name(&stub.name),
+                            ..EMPTY_STATUS
+                        },
+                        stub,
+                    })
+                    .collect(),
+                expected_damage: data.expected_damage,
+                actors,
+            },
+            rng: SimRngSource::new(seed, &data.rng).map_err(FromSimDataError::RollSequence)?,
+            actions,
+            report: data.report,
+            slidecast: data.slidecast,
+            mp_regen: data.mp_regen,
+            length: data.end,
+            log: Vec::new(),
+        })
+    }
+}
+
+impl Simulation {
+    pub fn builder(data: SimData) -> SimulationBuilder {
+        SimulationBuilder {
+            seed: batch::base_seed(&data),
+            data,
+            ais: HashMap::new(),
+        }
+    }
+
+    pub fn from_sim_data(data: SimData, seed: u64) -> Result<Self, FromSimDataError> {
+        Self::builder(data).seed(seed).build()
+    }
+
+    /// Runs the simulation until there are no more events or `end` is reached.
+    pub fn run(&mut self, end: u32) {
+        while self.step(end) {}
+    }
+
+    /// The time the fight ends, which is `end` from the config after the pull.
+    ///
+    /// With `pull_on_hit`, this moves with the first hit.
+    pub fn end(&self) -> u32 {
+        self.world.in_combat + self.length
+    }
+
+    /// Runs the simulation until the end of the fight.
+    pub fn run_fight(&mut self) {
+        while self.step(self.end()) {}
+    }
+
+    /// Handles the next event, returning `false` if the simulation has ended.
+    pub fn step(&mut self, end: u32) -> bool {
+        let Some((time, e)) = self.events.pop() else {
+            return false;
+        };
+
+        if time >= end {
+            return false;
+        }
+        // match &e {
+        //     SimEvent::Event(e) => match e {
+        //         Event::ActorTick(..) | Event::MpTick(..) => (),
+        //         _ => println!("[{:>4}.{:03}]: {:?}", time / 1000, time % 1000, e),
+        //     },
+        //     SimEvent::StartCast(id, action) => println!(
+        //         "[{:>4}.{:03}]: StartCast({:?}, {})",
+        //         time / 1000,
+        //         time % 1000,
+        //         id,
+        //         action.name()
+        //     ),
+        //     SimEvent::CastSnap(id, action) => println!(
+        //         "[{:>4}.{:03}]: CastSnap({:?}, {})",
+        //         time / 1000,
+        //         time % 1000,
+        //         id,
+        //         action.name()
+        //     ),
+        //     _ => println!("[{:>4}.{:03}]: {:?}", time / 1000, time % 1000, e),
+        // }
+
+        match self.world.time.cmp(&time) {
+            Ordering::Equal => (),
+            Ordering::Greater => panic!(
+                "world time ({}), event queue time ({})",
+                self.world.time, time
+            ),
+            Ordering::Less => self.world.advance(time - self.world.time, &mut self.events),
+        }
+
+        match e.clone() {
+            SimEvent::CheckStatusFalloff => (),
+            SimEvent::CdEnd(..) => (),
+            SimEvent::CastFailed(..) => (),
+            SimEvent::SimStart => (),
+            SimEvent::Other => (),
+            SimEvent::EspritIdk => {
+                if self.world.time >= self.world.in_combat {
+                    for (id, x) in self.world.actors.iter().enumerate() {
+                        if let Some(PlayerState {
+                            job: DynJob::Dnc,
+                            state,
+                            ..
+                        }) = &x.player
+                        {
+                            let mut state = state.borrow_mut();
+                            if let State::Dnc(v) = &mut *state {
+                                let iters = if x.statuses.contains_key(&(None, TECHNICAL_FINISH)) {
+                                    7
+                                } else {
+                                    1
+                                };
+
+                                let mut rng = self.rng.for_actor(ActorId(id as u16));
+
+                                for _ in 0..iters {
+                                    if rng.partner(0.08) {
+                                        let overcap = v.esprit > 90;
+                                        v.esprit += 10;
+                                        if self.report.esprit {
+                                            if overcap {
+                                                eprintln!("[warn] esprit overcapped from partner.");
+                                            }
+                                            println!("esprit: {}", v.esprit.value());
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                self.events
+                    .push(self.world.time + 1000, SimEvent::EspritIdk);
+            }
+            SimEvent::Event(e) => {
+                match e {
+                    Event::Action(..) => (),
+                    Event::ActorTick(id) => {
+                        if let Some(actor) = self.world.actors.get_mut(id.0 as usize) {
+                            let mut ticks = Vec::new();
+                            for ((_, status), effect) in actor.statuses.iter() {
+                                if let Some(snapshot) = &effect.snapshot {
+                                    let mut rng = self.rng.for_actor(effect.instance.source);
+                                    let damage = if self.world.expected_damage {
+                                        rng::expected_damage(
+                                            Some(snapshot.crit_chance),
+                                            Some(snapshot.dhit_chance),
+                                            |ch, dh, variance| {
+                                                snapshot.eot_result(ch, dh, variance)
+                                            },
+                                        )
+                                    } else {
+                                        snapshot.eot_result(
+                                            rng.random(CriticalHit::new(snapshot.crit_chance)),
+                                            rng.random(DirectHit::new(snapshot.dhit_chance)),
+                                            rng.random(DamageVariance::new()),
+                                        )
+                                    };
+
+                                    actor.damage += damage as u32;
+                                    ticks.push((effect.instance.source, *status, damage));
+                                }
+                            }
+                            self.events
+                                .push(time + 3000, SimEvent::Event(Event::ActorTick(id)));
+
+                            for (source, status, damage) in ticks {
+                                self.credit(source, id, DamageSource::Dot(status), damage);
+                                self.report(
+                                    time,
+                                    ReportKind::DotTick {
+                                        source,
+                                        target: id,
+                                        status,
+                                        damage,
+                                    },
+                                );
+                            }
+                        }
+                    }
+                    Event::AddMp(mp, id) => {
+                        if let Some(player) = self
+                            .world
+                            .actors
+                            .get_mut(id.0 as usize)
+                            .and_then(|a| a.player.as_mut())
+                        {
+                            let from = player.mp;
+                            player.mp = (player.mp + mp).min(10000);
+                            let to = player.mp;
+
+                            self.report(
+                                time,
+                                ReportKind::MpChange {
+                                    actor: id,
+                                    from,
+                                    to,
+                                },
+                            );
+                        }
+                    }
+                    Event::AdvCd(cdg, adv, id) => {
+                        if let Some(player) = self
+                            .world
+                            .actors
+                            .get_mut(id.0 as usize)
+                            .and_then(|a| a.player.as_mut())
+                        {
+                            if let Some(cd) = player.cooldowns.get_mut(cdg) {
+                                cd.advance(adv);
+                            }
+                            // the group is ready sooner, so there has to be an event to notice it.
+                            if let Some(ready) = player.cd_ready.iter_mut().find(|v| v.0 == cdg) {
+                                ready.1 = ready.1.saturating_sub(adv).max(time);
+                                self.events
+                                    .push(ready.1, SimEvent::CdEnd(CdEndEvent::JobCd(cdg)));
+                            }
+                        }
+                    }
+                    Event::Damage(DamageEvent {
+                        damage,
+                        target,
+                        source,
+                        action,
+                    }) => {
+                        if let Some(actor) = self.world.actors.get_mut(target.0 as usize) {
+                            actor.damage += damage as u32;
+                            self.credit(source, target, DamageSource::Action(action), damage);
+
+                            self.report(
+                                time,
+                                ReportKind::Damage {
+                                    source,
+                                    target,
+                                    action: Some(action),
+                                    damage,
+                                },
+                            )
+                        }
+                    }
+                    Event::Status(StatusEvent {
+                        kind,
+                        source,
+                        status,
+                        target,
+                    }) => {
+                        if let Some(target_actor) = self.world.actors.get_mut(target.0 as usize) {
+                            let key = (if status.unique { None } else { Some(source) }, status);
+                            let kind = match kind {
+                                StatusEventKind::FallOff => {
+                                    target_actor.statuses.shift_remove(&key);
+
+                                    StatusReportKind::Remove
+                                }
+                                StatusEventKind::Remove => {
+                                    target_actor.statuses.shift_remove(&key);
+
+                                    StatusReportKind::Remove
+                                }
+                                StatusEventKind::Apply { duration, stacks } => {
+                                    target_actor.statuses.insert(
+                                        key,
+                                        StatusEntry {
+                                            instance: StatusInstance {
+                                                source,
+                                                effect: status,
+                                                time: duration,
+                                                stack: stacks,
+                                            },
+                                            snapshot: None,
+                                        },
+                                    );
+
+                                    self.events
+                                        .push(time + duration, SimEvent::CheckStatusFalloff);
+
+                                    StatusReportKind::Apply { duration, stacks }
+                                }
+                                StatusEventKind::AddStacks { .. } => {
+                                    todo!("i don't remember what the semantics of this was supposed to be");
+                                }
+                                StatusEventKind::ApplyDot {
+                                    duration,
+                                    snapshot,
+                                    stacks,
+                                } => {
+                                    target_actor.statuses.insert(
+                                        key,
+                                        StatusEntry {
+                                            instance: StatusInstance {
+                                                source,
+                                                effect: status,
+                                                time: duration,
+                                                stack: stacks,
+                                            },
+                                            snapshot: Some(snapshot),
+                                        },
+                                    );
+
+                                    self.events
+                                        .push(time + duration, SimEvent::CheckStatusFalloff);
+
+                                    StatusReportKind::Apply { duration, stacks }
+                                }
+                                StatusEventKind::RemoveStacks { stacks } => {
+                                    if let Some(entry) = target_actor.statuses.get_mut(&key) {
+                                        entry.instance.sub_stacks(stacks);
+                                        if entry.instance.stack == 0 {
+                                            target_actor.statuses.shift_remove(&key);
+                                        }
+                                    }
+
+                                    StatusReportKind::RemoveStacks { stacks }
+                                }
+                                StatusEventKind::ApplyOrExtend {
+                                    duration,
+                                    stacks,
+                                    max,
+                                } => {
+                                    if let Some(entry) = target_actor.statuses.get_mut(&key) {
+                                        let from = entry.instance.time;
+
+                                        entry.instance.time =
+                                            (entry.instance.time + duration).min(max);
+
+                                        let to = entry.instance.time;
+
+                                        entry.instance.stack = stacks;
+
+                                        self.events.push(time + to, SimEvent::CheckStatusFalloff);
+
+                                        StatusReportKind::ExtendDuration {
+                                            duration,
+                                            stacks,
+                                            from,
+                                            to,
+                                        }
+                                    } else {
+                                        target_actor.statuses.insert(
+                                            key,
+                                            StatusEntry {
+                                                instance: StatusInstance {
+                                                    source,
+                                                    effect: status,
+                                                    time: duration,
+                                                    stack: stacks,
+                                                },
+                                                snapshot: None,
+                                            },
+                                        );
+
+                                        StatusReportKind::Apply { duration, stacks }
+                                    }
+                                }
+                                StatusEventKind::ApplyOrAddStacks {
+                                    duration,
+                                    stacks,
+                                    max,
+                                } => {
+                                    if let Some(entry) = target_actor.statuses.get_mut(&key) {
+                                        entry.instance.time = duration;
+
+                                        let from = entry.instance.stack;
+
+                                        entry.instance.add_stacks(stacks, max);
+
+                                        let to = entry.instance.stack;
+
+                                        StatusReportKind::AddStacks {
+                                            from,
+                                            to,
+                                            duration,
+                                            stacks,
+                                        }
+                                    } else {
+                                        target_actor.statuses.insert(
+                                            key,
+                                            StatusEntry {
+                                                instance: StatusInstance {
+                                                    source,
+                                                    effect: status,
+                                                    time: duration,
+                                                    stack: stacks,
+                                                },
+                                                snapshot: None,
+                                            },
+                                        );
+
+                                        StatusReportKind::Apply { duration, stacks }
+                                    }
+                                }
+                            };
+                            if status == MEDICATED {
+                                target_actor.rebuild_math();
+                            }
+                            self.report(
+                                time,
+                                ReportKind::Status {
+                                    status,
+                                    source,
+                                    target,
+                                    kind,
+                                },
+                            )
+                        }
+                    }
+                    Event::MpTick(id) => {
+                        if let Some(actor) = self.world.actors.get_mut(id.0 as usize) {
+                            // statuses like lucid dreaming add to every tick.
+                            let regen: u16 = actor
+                                .statuses
+                                .keys()
+                                .filter_map(|(_, effect)| {
+                                    self.mp_regen.iter().find(|v| v.status == effect.name)
+                                })
+                                .map(|v| v.mp)
+                                .sum();
+                            if let Some(player) = actor.player.as_mut() {
+                                let from = player.mp;
+
+                                let tick = player.math.mp_regen() as u16 + regen;
+                                player.mp = (player.mp + tick).min(10000);
+
+                                let to = player.mp;
+
+                                self.events
+                                    .push(time + 3000, SimEvent::Event(Event::MpTick(id)));
+
+                                self.report(
+                                    time,
+                                    ReportKind::MpTick {
+                                        actor: id,
+                                        from,
+                                        to,
+                                        tick,
+                                    },
+                                )
+                            }
+                        }
+                    }
+                    Event::Job(ref job_event, actor) => self.report(
+                        time,
+                        ReportKind::JobEvent {
+                            event: job_event.clone(),
+                            actor,
+                        },
+                    ), // just a job event, that gets handled down below.
+                }
+
+                for (actor, player) in self
+                    .world
+                    .iter_actors()
+                    .filter_map(|actor| actor.actor.player.as_ref().map(|v| (actor, v)))
+                {
+                    let mut state = { player.state.borrow().clone() };
+
+                    let mut event_sink =
+                        SimEventSink::new(actor, &mut self.rng, &mut self.events, time);
+
+                    player
+                        .job
+                        .event(&mut state, &&self.world, &e, &mut event_sink);
+
+                    *player.state.borrow_mut() = state;
+                }
+            }
+            SimEvent::Targetable(id) => {
+                if let Some(actor) = self.world.actors.get_mut(id.0 as usize) {
+                    actor.targetable = true;
+
+                    self.report(
+                        time,
+                        ReportKind::Target {
+                            actor: id,
+                            can_target: true,
+                        },
+                    );
+                    self.resume_autos(time);
+                }
+            }
+            SimEvent::RaidBuff(index, duration) => {
+                if let Some(buff) = self.world.raid_buffs.get(index) {
+                    // the buff comes from nobody that is simulated, so each player applies it to itself.
+                    for (id, _) in self
+                        .world
+                        .actors
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, actor)| actor.player.is_some())
+                    {
+                        let id = ActorId(id as u16);
+                        self.events.push(
+                            time,
+                            SimEvent::Event(Event::Status(StatusEvent {
+                                kind: StatusEventKind::Apply {
+                                    duration,
+                                    stacks: 1,
+                                },
+                                source: id,
+                                status: buff.status,
+                                target: id,
+                            })),
+                        );
+                    }
+                }
+            }
+            SimEvent::UsePotion(id) => {
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    match player.tincture {
+                        _ if player.casting.is_some() => {
+                            eprintln!("[warn] tincture used during a cast bar.")
+                        }
+                        Some(tincture) if player.tincture_ready <= time => {
+                            player.tincture_ready = time + tincture.cooldown;
+
+                            player.lock = player.lock.max(POTION_LOCK);
+                            self.events
+                                .push(time + player.lock as u32, SimEvent::CdEnd(CdEndEvent::Lock));
+                            self.events.push(
+                                time,
+                                SimEvent::Event(Event::Status(StatusEvent {
+                                    kind: StatusEventKind::Apply {
+                                        duration: tincture.duration,
+                                        stacks: 1,
+                                    },
+                                    source: id,
+                                    status: MEDICATED,
+                                    target: id,
+                                })),
+                            );
+                        }
+                        Some(_) => eprintln!("[warn] tincture used while on cooldown."),
+                        None => eprintln!("[warn] tincture used without one configured."),
+                    }
+                }
+            }
+            SimEvent::Disengage(id) => {
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    player.engaged = false;
+                }
+            }
+            SimEvent::Engage(id) => {
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    player.engaged = true;
+                    self.resume_autos(time);
+                }
+            }
+            SimEvent::CastBarEnd(id, action) => {
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    // an interrupted cast won't be the one in progress anymore.
+                    if player
+                        .casting
+                        .is_some_and(|v| v.action == action && v.snap == time)
+                    {
+                        player.casting = None;
+                        self.events.push(time, SimEvent::CastSnap(id, action));
+                    }
+                }
+            }
+            SimEvent::MoveStart(id) => {
+                let slidecast = self.slidecast;
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    player.moving = true;
+                    if let Some(bar) = player
+                        .casting
+                        .filter(|v| time < v.snap.saturating_sub(slidecast))
+                    {
+                        // the gcd and animation lock of an interrupted cast are given back,
+                        // along with the mp and cooldowns it spent.
+                        player.casting = None;
+                        player.gcd = 0;
+                        player.lock = 0;
+                        let mp = (player.mp, (player.mp + bar.mp).min(10000));
+                        player.mp = mp.1;
+                        for refund in bar.cooldowns.into_iter().flatten() {
+                            if let Some(cd_state) = player.cooldowns.get_mut(refund.group) {
+                                cd_state.advance(refund.cd);
+                            }
+                            player.set_cd_ready(refund.group, refund.ready.unwrap_or(time));
+                            self.events
+                                .push(time, SimEvent::CdEnd(CdEndEvent::JobCd(refund.group)));
+                        }
+                        if mp.0 != mp.1 {
+                            self.report(
+                                time,
+                                ReportKind::MpChange {
+                                    actor: id,
+                                    from: mp.0,
+                                    to: mp.1,
+                                },
+                            );
+                        }
+                        self.events.push(time, SimEvent::CdEnd(CdEndEvent::Gcd));
+                        self.events.push(time, SimEvent::CdEnd(CdEndEvent::Lock));
+                        self.events.push(
+                            time,
+                            SimEvent::CastFailed(id, bar.action, CastFailure::Interrupted),
+                        );
+                        self.report(
+                            time,
+                            ReportKind::CastInterrupted {
+                                source: id,
+                                action: bar.action,
+                            },
+                        );
+                    }
+                }
+            }
+            SimEvent::MoveEnd(id) => {
+                if let Some(player) = self
+                    .world
+                    .actors
+                    .get_mut(id.0 as usize)
+                    .and_then(|v| v.player.as_mut())
+                {
+                    player.moving = false;
+                }
+            }
+            SimEvent::Untargetable(id) => {
+                if let Some(actor) = self.world.actors.get_mut(id.0 as usize) {
+                    actor.targetable = false;
+
+                    self.report(
+                        time,
+                        ReportKind::Target {
+                            actor: id,
+                            can_target: false,
+                        },
+                    )
+                }
+            }
+            SimEvent::StartCast(id, action) => {
+                if let Some((actor, player)) = self
+                    .world
+                    .actors
+                    .get(id.0 as usize)
+                    .and_then(|actor| actor.player.as_ref().map(|v| (actor, v)))
+                {
+                    let (recast, lock, cooldowns, mp) = match action {
+                        Action::Job(action) => {
+                            let state = player.state.borrow();
+
+                            let handle = ActorHandle {
+                                actor,
+                                id,
+                                world: &self.world,
+                            };
+
+                            let info =
+                                match player.job.check_cast(action, &state, &&self.world, handle) {
+                                    Ok(info) => {
+                                        let cooldown = [info.cd, info.alt_cd]
+                                            .into_iter()
+                                            .flatten()
+                                            .find_map(|(cdg, cd, charges)| {
+                                                let cd_state = player.cooldowns.get(cdg)?;
+                                                (!cd_state.available(cd, charges)).then(|| {
+                                                    CastFailure::Cooldown(
+                                                        cdg,
+                                                        cd_state.cd_until(cd, charges),
+                                                    )
+                                                })
+                                            });
+                                        if info.snap > 0 && player.moving {
+                                            Err(CastFailure::Moving)
+                                        } else if player.casting.is_some() {
+                                            Err(CastFailure::Casting)
+                                        } else if player.mp < info.mp {
+                                            Err(CastFailure::Mp {
+                                                cost: info.mp,
+                                                mp: player.mp,
+                                            })
+                                        } else if let Some(cooldown) = cooldown {
+                                            Err(cooldown)
+                                        } else {
+                                            Ok(info)
+                                        }
+                                    }
+                                    Err(e) => Err(CastFailure::Rejected(e)),
+                                };
+
+                            drop(state);
+
+                            let info = match info {
+                                Ok(info) => info,
+                                Err(failure) => {
+                                    // the ai is told on its own event, since nothing happened here.
+                                    self.events.push(
+                                        time,
+                                        SimEvent::CastFailed(id, Action::Job(action), failure),
+                                    );
+                                    return true;
+                                }
+                            };
+
+                            // dear god.
+
+                            let player = self
+                                .world
+                                .actors
+                                .get_mut(id.0 as usize)
+                                .unwrap()
+                                .player
+                                .as_mut()
+                                .unwrap();
+
+                            let gcd_left = info.gcd.max(player.gcd);
+                            let lock_left = info.lock;
+
+                            // println!("{}", gcd_left);
+
+                            player.gcd = gcd_left;
+                            player.lock = lock_left;
+
+                            self.events
+                                .push(time + player.lock as u32, SimEvent::CdEnd(CdEndEvent::Lock));
+
+                            self.events
+                                .push(time + player.gcd as u32, SimEvent::CdEnd(CdEndEvent::Gcd));
+
+                            let mut cooldowns = Vec::new();
+                            let mut refunds = [None; 2];
+
+                            let mp = (player.mp, player.mp - info.mp);
+                            player.mp = mp.1;
+
+                            if let Some((cdg, cd, charges)) = info.cd {
+                                // println!("{:?} {:?} {:?}", cdg, cd, charges);
+                                refunds[0] = Some(player.cd_refund(cdg, cd as u32));
+                                if let Some(cd_state) = player.cooldowns.get_mut(cdg) {
+                                    cd_state.apply(cd, charges);
+
+                                    let until = cd_state.cd_until(cd, charges);
+                                    cooldowns.push((cdg, until));
+                                    player.set_cd_ready(cdg, time + until);
+
+                                    self.events.push(
+                                        time + until,
+                                        SimEvent::CdEnd(CdEndEvent::JobCd(cdg)),
+                                    );
+                                }
+                            }
+
+                            if let Some((cdg, cd, charges)) = info.alt_cd {
+                                // println!("{:?} {:?} {:?}", cdg, cd, charges);
+                                refunds[1] = Some(player.cd_refund(cdg, cd as u32));
+                                if let Some(cd_state) = player.cooldowns.get_mut(cdg) {
+                                    cd_state.apply(cd, charges);
+
+                                    let until = cd_state.cd_until(cd, charges);
+                                    cooldowns.push((cdg, until));
+                                    player.set_cd_ready(cdg, time + until);
+
+                                    self.events.push(
+                                        time + until,
+                                        SimEvent::CdEnd(CdEndEvent::JobCd(cdg)),
+                                    );
+                                }
+                            }
+
+                            if info.snap > 0 {
+                                let snap = time + info.snap as u32;
+                                player.casting = Some(CastBar {
+                                    action: Action::Job(action),
+                                    start: time,
+                                    snap,
+                                    mp: info.mp,
+                                    cooldowns: refunds,
+                                });
+                                self.events
+                                    .push(snap, SimEvent::CastBarEnd(id, Action::Job(action)));
+                            } else {
+                                self.events
+                                    .push(time, SimEvent::CastSnap(id, Action::Job(action)));
+                            }
+
+                            (player.gcd, player.lock, cooldowns, mp)
+                        }
+                    };
+
+                    if mp.0 != mp.1 {
+                        self.report(
+                            time,
+                            ReportKind::MpChange {
+                                actor: id,
+                                from: mp.0,
+                                to: mp.1,
+                            },
+                        );
+                    }
+
+                    self.report(
+                        time,
+                        ReportKind::CastStart {
+                            source: id,
+                            action,
+                            recast,
+                            lock,
+                            cooldowns,
+                        },
+                    )
+                }
+            }
+            SimEvent::CastSnap(id, action) => {
+                if let Some((actor, player)) = self
+                    .world
+                    .actors
+                    .get(id.0 as usize)
+                    .and_then(|actor| actor.player.as_ref().map(|v| (actor, v)))
+                {
+                    match action {
+                        Action::Job(action) => {
+                            let mut state = { player.state.borrow().clone() };
+
+                            let mut event_sink = SimEventSink::new(
+                                ActorHandle {
+                                    actor,
+                                    id,
+                                    world: &self.world,
+                                },
+                                &mut self.rng,
+                                &mut self.events,
+                                time,
+                            );
+
+                            player
+                                .job
+                                .cast_snap(action, &mut state, &&self.world, &mut event_sink)
+                                .unwrap();
+
+                            // println!("{:?} @ {}: {:?}", action, self.world.time, state);
+
+                            *(player.state.borrow_mut()) = state;
+                        }
+                    }
+
+                    self.report(time, ReportKind::CastSnap { source: id, action })
+                }
+            }
+            SimEvent::AutoAttack(id) => {
+                if let Some((actor, player)) = self
+                    .world
+                    .actors
+                    .get(id.0 as usize)
+                    .and_then(|actor| actor.player.as_ref().map(|v| (actor, v)))
+                    // a swing that was moved or paused has a stale event left over.
+                    .filter(|(_, player)| player.next_auto == Some(time))
+                {
+                    let handle = ActorHandle {
+                        actor,
+                        id,
+                        world: &self.world,
+                    };
+
+                    let target = handle
+                        .target()
+                        .filter(|target| target.actor.targetable)
+                        .map(|target| target.id());
+
+                    let (swing, next) = match (player.casting, target) {
+                        // autos wait for the cast bar to finish.
+                        (Some(bar), _) => (None, Some(bar.snap)),
+                        (None, Some(target)) if player.engaged => {
+                            let damage = handle.auto_damage(target, &mut self.rng.for_actor(id));
+                            (Some((target, damage)), Some(time + handle.auto_delay()))
+                        }
+                        // paused until the player engages again or the target comes back.
+                        _ => (None, None),
+                    };
+
+                    if let Some(next) = next {
+                        self.events.push(next, SimEvent::AutoAttack(id));
+                    }
+                    if let Some(player) = self.world.actors[id.0 as usize].player.as_mut() {
+                        player.next_auto = next;
+                    }
+
+                    if let Some((target, damage)) = swing {
+                        self.world.actors[target.0 as usize].damage += damage as u32;
+                        self.credit(id, target, DamageSource::Auto, damage);
+
+                        self.report(
+                            time,
+                            ReportKind::Damage {
+                                source: id,
+                                target,
+                                action: None,
+                                damage,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut to_remove = vec![];
+
+        for (id, actions) in self.actions.iter_mut() {
+            let actor = &self.world.actors[id.0 as usize];
+
+            let handle = ActorHandle {
+                actor,
+                id: *id,
+                world: &self.world,
+            };
+
+            if actions.0.next(&e, &mut self.events, handle, time) {
+                to_remove.push(*id);
+            }
+        }
+
+        for x in to_remove {
+            self.actions.shift_remove(&x);
+        }
+
+        true
+    }
+
+    // adds damage to what `source` dealt to `target`.
+    // the first hit starts combat if the world is waiting on it,
+    // and anything that lands before combat starts isn't counted.
+    fn credit(&mut self, source: ActorId, target: ActorId, kind: DamageSource, damage: u64) {
+        if self.world.pull_on_hit {
+            self.world.pull_on_hit = false;
+            self.world.in_combat = self.world.time;
+
+            let buffs: Vec<_> = self
+                .world
+                .raid_buffs
+                .iter()
+                .map(|v| v.stub.clone())
+                .collect();
+            let time = self.world.time;
+            schedule_raid_buffs(&mut self.events, &buffs, time, time, self.length);
+        }
+        if self.world.time < self.world.in_combat {
+            return;
+        }
+        if let Some(actor) = self.world.actors.get_mut(source.0 as usize) {
+            actor.dealt.add(target, kind, damage);
+        }
+    }
+
+    // restarts the auto attacks of every player that paused them.
+    // they swing right away, since the swing they paused on was already due.
+    fn resume_autos(&mut self, time: u32) {
+        for (id, actor) in self.world.actors.iter_mut().enumerate() {
+            if let Some(player) = actor.player.as_mut() {
+                if player.engaged && player.next_auto.is_none() {
+                    player.next_auto = Some(time);
+                    self.events
+                        .push(time, SimEvent::AutoAttack(ActorId(id as u16)));
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, time: u32, kind: ReportKind) {
+        let print = match kind {
+            ReportKind::MpTick { .. } | ReportKind::MpChange { .. } => self.report.mp_tick,
+            ReportKind::Damage { .. } | ReportKind::DotTick { .. } => self.report.damage,
+            ReportKind::Status { .. } => self.report.status,
+            ReportKind::CastStart { .. } => self.report.cast_start,
+            ReportKind::CastSnap { .. } => self.report.cast_snap,
+            ReportKind::JobEvent { .. } => self.report.job_event,
+            ReportKind::Target { .. } => self.report.target,
+            ReportKind::CastInterrupted { .. } => self.report.interrupt,
+        };
+        if print {
+            print!("{:>9}: ", CombatTime::new(time, &self.world));
+            println!(
+                "{}",
+                ReportData {
+                    kind: &kind,
+                    world: &self.world
+                }
+            );
+        }
+        self.log.push((time, kind));
+    }
+}
+
+// pushes every use of `buffs` relative to `pull`, until `length` after it.
+// anything before `from` can't be scheduled anymore, so only the part after it is kept.
+fn schedule_raid_buffs(
+    events: &mut RadixEventQueue<SimEvent>,
+    buffs: &[BuffStub],
+    pull: u32,
+    from: u32,
+    length: u32,
+) {
+    let end = (pull + length) as i64;
+    for (index, buff) in buffs.iter().enumerate() {
+        let mut start = pull as i64 + buff.start as i64;
+        while start < end {
+            let stop = start + buff.duration as i64;
+            if stop > from as i64 {
+                let time = start.max(from as i64) as u32;
+                events.push(time, SimEvent::RaidBuff(index, stop as u32 - time));
+            }
+            match buff.every {
+                Some(every) if every > 0 => start += every as i64,
+                _ => break,
+            }
+        }
+    }
+}
+
+/// A time relative to the pull, which displays as seconds and is negative before it.
+#[derive(Clone, Copy, Debug)]
+pub struct CombatTime(pub i64);
+
+impl CombatTime {
+    pub fn new(time: u32, world: &WorldState) -> Self {
+        Self(time as i64 - world.in_combat as i64)
+    }
+}
+
+impl fmt::Display for CombatTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        f.pad(&format!("{}{}.{:03}", sign, abs / 1000, abs % 1000))
+    }
+}
+
+/// A report along with the world it was made in, which displays the names of the actors in it.
+pub struct ReportData<'w> {
+    pub kind: &'w ReportKind,
+    pub world: &'w WorldState,
+}
+
+impl<'w> fmt::Display for ReportData<'w> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ReportKind::*;
+        let name = |id: ActorId| {
+            self.world
+                .actors
+                .get(id.0 as usize)
+                .map(|v| v.name.as_str())
+                .unwrap_or_default()
+        };
+        match self.kind {
+            MpTick {
+                actor,
+                from,
+                to,
+                tick,
+            } => f
+                .debug_struct("MpTick")
+                .field("actor", &name(*actor))
+                .field("from", from)
+                .field("to", to)
+                .field("tick", tick)
+                .finish(),
+            MpChange { actor, from, to } => f
+                .debug_struct("MpChange")
+                .field("actor", &name(*actor))
+                .field("from", from)
+                .field("to", to)
+                .finish(),
+            Damage {
+                source,
+                target,
+                action,
+                damage,
+            } => {
+                let mut f = f.debug_struct("Damage");
+                f.field("source", &name(*source))
+                    .field("target", &name(*target));
+                match action {
+                    Some(action) => f.field("action", &action.name()),
+                    // auto attacks don't come from an action.
+                    None => f.field("action", &"Auto Attack"),
+                };
+                f.field("damage", damage).finish()
+            }
+            DotTick {
+                source,
+                target,
+                status,
+                damage,
+            } => f
+                .debug_struct("DotTick")
+                .field("source", &name(*source))
+                .field("target", &name(*target))
+                .field("status", &status.name)
+                .field("damage", damage)
+                .finish(),
+            Status {
+                status,
+                source,
+                target,
+                kind,
+            } => f
+                .debug_struct("Status")
+                .field("status", &status.name)
+                .field("source", &name(*source))
+                .field("target", &name(*target))
+                .field("kind", kind)
+                .finish(),
+            CastStart { source, action, .. } => f
+                .debug_struct("CastStart")
+                .field("source", &name(*source))
+                .field("action", &action.name())
+                .finish(),
+            CastSnap { source, action } => f
+                .debug_struct("CastSnap")
+                .field("source", &name(*source))
+                .field("action", &action.name())
+                .finish(),
+            JobEvent { event, actor } => f
+                .debug_struct("JobEvent")
+                .field("event", event)
+                .field("actor", &name(*actor))
+                .finish(),
+            Target { actor, can_target } => f
+                .debug_struct("Target")
+                .field("actor", &name(*actor))
+                .field("can_target", can_target)
+                .finish(),
+            CastInterrupted { source, action } => f
+                .debug_struct("CastInterrupted")
+                .field("source", &name(*source))
+                .field("action", &action.name())
+                .finish(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ReportKind {
+    MpTick {
+        actor: ActorId,
+        from: u16,
+        to: u16,
+        tick: u16,
+    },
+    // mp that was spent or gained outside of a tick.
+    MpChange {
+        actor: ActorId,
+        from: u16,
+        to: u16,
+    },
+    Damage {
+        source: ActorId,
+        target: ActorId,
+        // `None` for auto attacks.
+        action: Option<Action>,
+        damage: u64,
+    },
+    // a tick of damage over time from a status on `target`.
+    DotTick {
+        source: ActorId,
+        target: ActorId,
+        status: StatusEffect,
+        damage: u64,
+    },
+    Status {
+        status: StatusEffect,
+        source: ActorId,
+        target: ActorId,
+        kind: StatusReportKind,
+    },
+    CastStart {
+        source: ActorId,
+        action: Action,
+        // the gcd recast and animation lock the cast started with.
+        recast: u16,
+        lock: u16,
+        // the time until each cooldown group used by the cast is usable again.
+        cooldowns: Vec<(CdGroup, u32)>,
+    },
+    CastSnap {
+        source: ActorId,
+        action: Action,
+    },
+    JobEvent {
+        event: JobEvent,
+        actor: ActorId,
+    },
+    Target {
+        actor: ActorId,
+        can_target: bool,
+    },
+    CastInterrupted {
+        source: ActorId,
+        action: Action,
+    },
+}
+
+#[derive(Clone, Debug)]
+#[allow(unused)]
+pub enum StatusReportKind {
+    Apply {
+        duration: u32,
+        stacks: u8,
+    },
+    Remove,
+    NaturalRemove,
+    RemoveStacks {
+        stacks: u8,
+    },
+    ExtendDuration {
+        from: u32,
+        to: u32,
+        duration: u32,
+        stacks: u8,
+    },
+    AddStacks {
+        from: u8,
+        to: u8,
+        duration: u32,
+        stacks: u8,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct WorldState {
+    pub time: u32,
+    pub in_combat: u32,
+    // if true, combat hasn't started yet and starts with the next hit on an enemy.
+    pub pull_on_hit: bool,
+    // if true, damage is the average over crits and direct hits instead of being rolled.
+    pub expected_damage: bool,
+    pub actors: Vec<ActorState>,
+    // raid buffs from party members that aren't simulated.
+    pub raid_buffs: Vec<RaidBuffState>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RaidBuffState {
+    pub stub: BuffStub,
+    // the status the buff puts on every player while it is up.
+    pub status: StatusEffect,
+}
+
+// a status that does nothing on its own, for the statuses the sim makes up itself.
+// it copies last dance ready, which is only a proc, as xivc-core has no empty status.
+// the effects of the statuses made from it are handled by the sim instead.
+const EMPTY_STATUS: StatusEffect = StatusEffect {
+    name: "",
+    unique: true,
+    ..LAST_DANCE_READY
+};
+
+// statuses need a `&'static str` name, so names from the config are leaked.
+// every sim in a batch uses the same names, so each one is only leaked once.
+fn static_name(name: &str) -> &'static str {
+    static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut names = NAMES.lock().unwrap();
+    match names.iter().find(|v| **v == name) {
+        Some(v) => v,
+        None => {
+            let v: &'static str = Box::leak(name.into());
+            names.push(v);
+            v
+        }
+    }
+}
+
+/// The status a tincture puts on the player who drank it, which adds its main stat while it is up.
+pub const MEDICATED: StatusEffect = StatusEffect {
+    name: "Medicated",
+    ..EMPTY_STATUS
+};
+
+/// The total of every raid buff on a player, which is applied to their damage.
+#[derive(Clone, Copy, Debug)]
+pub struct RaidBonus {
+    // in thousandths, like hit chances.
+    pub crit: u16,
+    pub dhit: u16,
+    // the damage multiplier, in thousandths.
+    pub damage: u32,
+}
+
+impl Default for RaidBonus {
+    fn default() -> Self {
+        Self {
+            crit: 0,
+            dhit: 0,
+            damage: 1000,
+        }
+    }
+}
+
+impl RaidBonus {
+    pub fn crit(&self, chance: u16) -> u16 {
+        chance.saturating_add(self.crit)
+    }
+
+    pub fn dhit(&self, chance: u16) -> u16 {
+        chance.saturating_add(self.dhit)
+    }
+
+    pub fn scale(&self, damage: u64) -> u64 {
+        damage * self.damage as u64 / 1000
+    }
+
+    // dots snapshot raid buffs when they are applied, like any other buff.
+    fn snapshot(&self, snapshot: EotSnapshot) -> EotSnapshot {
+        EotSnapshot {
+            base: self.scale(snapshot.base),
+            crit_chance: self.crit(snapshot.crit_chance),
+            dhit_chance: self.dhit(snapshot.dhit_chance),
+            ..snapshot
+        }
+    }
+}
+
+impl WorldState {
+    /// The total of every raid buff that is on `actor` right now.
+    pub fn raid_bonus(&self, actor: &ActorState) -> RaidBonus {
+        self.raid_buffs
+            .iter()
+            .filter(|v| actor.statuses.contains_key(&(None, v.status)))
+            .fold(RaidBonus::default(), |bonus, v| RaidBonus {
+                crit: bonus.crit + v.stub.crit * 10,
+                dhit: bonus.dhit + v.stub.dhit * 10,
+                // damage buffs multiply with each other, like they do in game.
+                damage: bonus.damage * (100 + v.stub.damage as u32) / 100,
+            })
+    }
+
+    fn advance(&mut self, time: u32, queue: &mut RadixEventQueue<SimEvent>) {
+        for (id, actor) in self.actors.iter_mut().enumerate() {
+            if let Some(player) = &mut actor.player {
+                player.cooldowns.iter_mut().for_each(|x| x.0.advance(time));
+                player.gcd = (player.gcd as u32).saturating_sub(time) as u16;
+                player.lock = (player.lock as u32).saturating_sub(time) as u16;
+                player.state.borrow_mut().advance(time);
+            }
+            let mut to_remove = Vec::new();
+            for (key, status) in actor.statuses.iter_mut() {
+                let time_left = status.instance.time;
+                status.instance.advance(time);
+                if status.instance.time == 0 {
+                    to_remove.push((*key, time_left));
+                }
+            }
+            for (x, t) in &to_remove {
+                queue.push(
+                    self.time + t,
+                    SimEvent::Event(Event::Status(StatusEvent {
+                        kind: StatusEventKind::FallOff,
+                        status: x.1,
+                        source: ActorId(id as u16),
+                        target: ActorId(id as u16),
+                    })),
+                );
+                actor.statuses.shift_remove(x);
+            }
+            if to_remove.iter().any(|(x, _)| x.1 == MEDICATED) {
+                actor.rebuild_math();
+            }
+        }
+        self.time += time;
+    }
+}
+
+impl WorldState {
+    fn iter_actors(&self) -> impl Iterator<Item = ActorHandle<'_>> {
+        self.actors
+            .iter()
+            .enumerate()
+            .map(|(id, actor)| ActorHandle {
+                world: self,
+                id: ActorId(id as u16),
+                actor,
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActorState {
+    // The name of the actor.
+    pub name: String,
+    // true if the actor can be targeted.
+    pub targetable: bool,
+    // the amount of total damage taken.
+    pub damage: u32,
+    // the damage this actor dealt, including damage over time.
+    pub dealt: DamageLedger,
+    // the statuses an actor has, in the order they were applied.
+    // the order has to be stable, as dot ticks and falloffs happen in it.
+    pub statuses: IndexMap<(Option<ActorId>, StatusEffect), StatusEntry>,
+    // if this actor is a player, the corresponding state.
+    pub player: Option<PlayerState>,
+
+    pub target: Option<ActorId>,
+}
+
+/// The damage dealt by a single actor in combat, split up by target and where it came from.
+#[derive(Clone, Debug, Default)]
+pub struct DamageLedger {
+    pub total: u64,
+    pub entries: Vec<DamageEntry>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DamageEntry {
+    pub target: ActorId,
+    pub source: DamageSource,
+    pub damage: u64,
+    pub hits: u32,
+}
+
+/// What dealt some damage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageSource {
+    Action(Action),
+    Dot(StatusEffect),
+    Auto,
+}
+
+/// The kind of damage a [`DamageSource`] deals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageCategory {
+    Direct,
+    Dot,
+    Auto,
+}
+
+impl DamageSource {
+    pub fn category(&self) -> DamageCategory {
+        match self {
+            Self::Action(_) => DamageCategory::Direct,
+            Self::Dot(_) => DamageCategory::Dot,
+            Self::Auto => DamageCategory::Auto,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Action(action) => action.name(),
+            Self::Dot(status) => status.name,
+            Self::Auto => "Auto Attack",
+        }
+    }
+}
+
+impl DamageLedger {
+    fn add(&mut self, target: ActorId, source: DamageSource, damage: u64) {
+        self.total += damage;
+        match self
+            .entries
+            .iter_mut()
+            .find(|v| v.target == target && v.source == source)
+        {
+            Some(entry) => {
+                entry.damage += damage;
+                entry.hits += 1;
+            }
+            None => self.entries.push(DamageEntry {
+                target,
+                source,
+                damage,
+                hits: 1,
+            }),
+        }
+    }
+
+    /// The damage dealt to `target`.
+    pub fn to_target(&self, target: ActorId) -> u64 {
+        self.entries
+            .iter()
+            .filter(|v| v.target == target)
+            .map(|v| v.damage)
+            .sum()
+    }
+
+    /// The damage dealt of a single category.
+    pub fn of_category(&self, category: DamageCategory) -> u64 {
+        self.entries
+            .iter()
+            .filter(|v| v.source.category() == category)
+            .map(|v| v.damage)
+            .sum()
+    }
+
+    /// The damage per second between combat starting in `world` and `end`.
+    pub fn dps(&self, world: &WorldState, end: u32) -> f64 {
+        let duration = end.saturating_sub(world.in_combat).max(1);
+        self.total as f64 / (duration as f64 / 1000.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StatusEntry {
+    pub instance: StatusInstance,
+    pub snapshot: Option<EotSnapshot>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerState {
+    pub job: DynJob,
+    // the job `job` is the logic for.
+    pub job_kind: Job,
+    pub gcd: u16,
+    pub lock: u16,
+    pub mp: u16,
+    pub cooldowns: CdMap<ActionCd>,
+    // the time each cooldown group that has been used can be used again.
+    pub cd_ready: Vec<(CdGroup, u32)>,
+    // the cast bar in progress, if there is one.
+    pub casting: Option<CastBar>,
+    pub moving: bool,
+    // the weapon delay before haste.
+    pub auto_delay: u32,
+    // when the next auto attack will happen, or `None` while they are paused.
+    pub next_auto: Option<u32>,
+    // if the player is attacking their target at all.
+    pub engaged: bool,
+    pub state: RefCell<State>,
+    pub math: XivMath,
+    // the stats with food, which `math` is rebuilt from.
+    pub stats: PlayerStats,
+    pub weapon: WeaponInfo,
+    pub info: PlayerInfo,
+    pub tincture: Option<Tincture>,
+    // when the tincture is off cooldown.
+    pub tincture_ready: u32,
+}
+
+// the animation lock of drinking a tincture.
+const POTION_LOCK: u16 = 600;
+
+fn player_math(
+    stats: &PlayerStats,
+    weapon: &WeaponInfo,
+    info: &PlayerInfo,
+    tincture: Option<&Tincture>,
+) -> XivMath {
+    let mut stats = stats.clone();
+    if let Some(tincture) = tincture {
+        tincture.apply(&mut stats);
+    }
+    let mut math = XivMath::new(stats, weapon.clone(), info.clone());
+    math.ex_lock = 50;
+    math
+}
+
+/// A hard cast in progress.
+#[derive(Clone, Copy, Debug)]
+pub struct CastBar {
+    pub action: Action,
+    pub start: u32,
+    // the time the cast snapshots, which is the end of the cast bar.
+    pub snap: u32,
+    // the mp spent on the cast.
+    pub mp: u16,
+    // the cooldowns the cast used, for the main and alternate group.
+    pub cooldowns: [Option<CdRefund>; 2],
+}
+
+/// A cooldown a cast used, which is given back if the cast is interrupted.
+#[derive(Clone, Copy, Debug)]
+pub struct CdRefund {
+    pub group: CdGroup,
+    pub cd: u32,
+    // when the group was ready before the cast.
+    pub ready: Option<u32>,
+}
+
+impl ActorState {
+    // rebuilds the math of a player from their stats, with the tincture while they are medicated.
+    fn rebuild_math(&mut self) {
+        let medicated = self.statuses.contains_key(&(None, MEDICATED));
+        if let Some(player) = &mut self.player {
+            let tincture = player.tincture.as_ref().filter(|_| medicated);
+            player.math = player_math(&player.stats, &player.weapon, &player.info, tincture);
+        }
+    }
+}
+
+impl PlayerState {
+    fn cd_refund(&self, group: CdGroup, cd: u32) -> CdRefund {
+        CdRefund {
+            group,
+            cd,
+            ready: self.cd_ready.iter().find(|v| v.0 == group).map(|v| v.1),
+        }
+    }
+
+    fn set_cd_ready(&mut self, group: CdGroup, time: u32) {
+        match self.cd_ready.iter_mut().find(|v| v.0 == group) {
+            Some(v) => v.1 = time,
+            None => self.cd_ready.push((group, time)),
+        }
+    }
+}
+
+impl<'w> WorldRef<'w> for &'w WorldState {
+    type Actor = ActorHandle<'w>;
+
+    type DurationInfo = ActorDurInfo<'w>;
+
+    fn actor(&self, id: ActorId) -> Option<Self::Actor> {
+        self.actors.get(id.0 as usize).map(|state| ActorHandle {
+            actor: state,
+            id,
+            world: self,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ActorHandle<'w> {
+    pub actor: &'w ActorState,
+    pub id: ActorId,
+    pub world: &'w WorldState,
+}
+
+impl<'w> ActorHandle<'w> {
+    fn status_iter(&self) -> StatusIter<'w> {
+        StatusIter {
+            inner: Some(self.actor.statuses.values()),
+        }
+    }
+
+    // the time between auto attacks, with haste.
+    fn auto_delay(&self) -> u32 {
+        let Some(player) = self.actor.player.as_ref() else {
+            return 0;
+        };
+        let state = player.state.borrow();
+        let buffs = StatusSnapshot {
+            job: player.job.effect(&state),
+            source: self.actor.statuses.values().map(|status| status.instance),
+            target: iter::empty(),
+        };
+        buffs.haste(player.auto_delay as u64) as u32
+    }
+}
+
+impl<'w> ActorRef<'w> for ActorHandle<'w> {
+    type World = &'w WorldState;
+
+    fn id(&self) -> ActorId {
+        self.id
+    }
+
+    fn world(&self) -> Self::World {
+        self.world
+    }
+
+    fn attack_damage<R: EventRng>(
+        &self,
+        damage: DamageInstance,
+        target: ActorId,
+        rng: &mut R,
+    ) -> u64 {
+        // println!("{:?}", damage);
+        let DamageInstance {
+            potency,
+            dmg_el,
+            dmg_ty,
+            force_crit,
+            force_dhit,
+            falloff,
+        } = damage;
+        let state = self
+            .actor
+            .player
+            .as_ref()
+            .map(|v| (v.job, v.state.borrow()));
+        let buffs = StatusSnapshot::new(
+            self.status_iter(),
+            self.world
+                .actor(target)
+                .map(|target| target.status_iter())
+                .unwrap_or_default(),
+            state.as_ref().and_then(|(job, state)| job.effect(state)),
+        );
+        if let Some(player) = &self.actor.player {
+            let bonus = self.world.raid_bonus(self.actor);
+            if self.world.expected_damage {
+                let crit = bonus.crit(buffs.crit_chance(player.math.crit_chance()) as u16);
+                let dhit = bonus.dhit(buffs.dhit_chance(player.math.dhit_chance()) as u16);
+                return bonus.scale(rng::expected_damage(
+                    (!force_crit).then_some(crit),
+                    (!force_dhit).then_some(dhit),
+                    |ch, dh, variance| {
+                        player.math.action_damage(
+                            potency,
+                            dmg_ty,
+                            dmg_el,
+                            player.math.job_attack_stat(),
+                            ch,
+                            dh,
+                            falloff as u64,
+                            variance,
+                            &buffs,
+                        )
+                    },
+                ));
+            }
+            let ch = match force_crit {
+                true => HitTypeHandle::Force,
+                false => {
+                    let chance = bonus.crit(buffs.crit_chance(player.math.crit_chance()) as u16);
+                    rng.random(CriticalHit::new(chance))
+                }
+            };
+            let dh = match force_dhit {
+                true => HitTypeHandle::Force,
+                false => {
+                    let chance = bonus.dhit(buffs.dhit_chance(player.math.dhit_chance()) as u16);
+                    rng.random(DirectHit::new(chance))
+                }
+            };
+            bonus.scale(player.math.action_damage(
+                potency,
+                dmg_ty,
+                dmg_el,
+                player.math.job_attack_stat(),
+                ch,
+                dh,
+                falloff as u64,
+                rng.random(DamageVariance::new()),
+                &buffs,
+            ))
+        } else {
+            buffs.damage(potency, dmg_ty, dmg_el)
+        }
+    }
+
+    fn dot_damage_snapshot(
+        &self,
+        damage: DamageInstance,
+        stat: SpeedStat,
+        target: ActorId,
+    ) -> EotSnapshot {
+        let DamageInstance {
+            potency,
+            dmg_el,
+            dmg_ty,
+            ..
+        } = damage;
+        let state = self
+            .actor
+            .player
+            .as_ref()
+            .map(|v| (v.job, v.state.borrow()));
+        let buffs = StatusSnapshot::new(
+            self.status_iter(),
+            self.world
+                .actor(target)
+                .map(|target| target.status_iter())
+                .unwrap_or_default(),
+            state.as_ref().and_then(|(job, state)| job.effect(state)),
+        );
+        if let Some(player) = &self.actor.player {
+            let snapshot = player.math.dot_damage_snapshot(
+                potency,
+                dmg_ty,
+                dmg_el,
+                player.math.job_attack_stat(),
+                stat,
+                &buffs,
+            );
+            self.world.raid_bonus(self.actor).snapshot(snapshot)
+        } else {
+            EotSnapshot {
+                base: buffs.damage(potency, dmg_ty, dmg_el),
+                crit_chance: 0,
+                crit_damage: 0,
+                dhit_chance: 0,
+            }
+        }
+    }
+
+    fn auto_damage<R: EventRng>(&self, target: ActorId, rng: &mut R) -> u64 {
+        let Some(player) = self.actor.player.as_ref() else {
+            return 0;
+        };
+        let state = player.state.borrow();
+        let potency = player.job.job().aa_potency();
+        let dmg_ty = player.job.job().aa_type();
+        let buffs = StatusSnapshot::new(
+            self.status_iter(),
+            self.world
+                .actor(target)
+                .map(|target| target.status_iter())
+                .unwrap_or_default(),
+            player.job.effect(&state),
+        );
+        let bonus = self.world.raid_bonus(self.actor);
+        if self.world.expected_damage {
+            let crit = bonus.crit(buffs.crit_chance(player.math.crit_chance()) as u16);
+            let dhit = bonus.dhit(buffs.dhit_chance(player.math.dhit_chance()) as u16);
+            return bonus.scale(rng::expected_damage(
+                Some(crit),
+                Some(dhit),
+                |ch, dh, variance| {
+                    player.math.aa_damage(
+                        potency as u64,
+                        dmg_ty,
+                        DamageElement::None,
+                        ch,
+                        dh,
+                        variance,
+                        &buffs,
+                    )
+                },
+            ));
+        }
+        let ch = {
+            let chance = bonus.crit(buffs.crit_chance(player.math.crit_chance()) as u16);
+            rng.random(CriticalHit::new(chance))
+        };
+        let dh = {
+            let chance = bonus.dhit(buffs.dhit_chance(player.math.dhit_chance()) as u16);
+            rng.random(DirectHit::new(chance))
+        };
+        bonus.scale(player.math.aa_damage(
+            potency as u64,
+            dmg_ty,
+            DamageElement::None,
+            ch,
+            dh,
+            rng.random(DamageVariance::new()),
+            &buffs,
+        ))
+    }
+
+    fn statuses(&self) -> impl Iterator<Item = StatusInstance> + 'w {
+        self.actor.statuses.values().map(|status| status.instance)
+    }
+
+    fn target(&self) -> Option<Self> {
+        self.actor.target.and_then(|id| self.world.actor(id))
+    }
+
+    fn actors_for_action(
+        &self,
+        faction: Option<Faction>,
+        _: ActionTargetting,
+    ) -> impl Iterator<Item = Self> + 'w {
+        self.world.iter_actors().filter(move |handle| {
+            handle.actor.targetable
+                && match faction {
+                    Some(faction) => match faction {
+                        Faction::Enemy => handle.faction() == Faction::Enemy,
+                        Faction::Friendly => handle.faction() != Faction::Enemy,
+                        Faction::Party => handle.faction() == Faction::Party,
+                    },
+                    None => true,
+                }
+        })
+    }
+
+    fn within_range(&self, _: ActorId, _: ActionTargetting) -> bool {
+        true
+    }
+
+    fn mp(&self) -> u16 {
+        self.actor.player.as_ref().map(|v| v.mp).unwrap_or_default()
+    }
+
+    fn faction(&self) -> Faction {
+        if self.actor.player.is_some() {
+            Faction::Party
+        } else {
+            Faction::Enemy
+        }
+    }
+
+    fn check_positional(&self, _: Positional, _: ActorId) -> bool {
+        true
+    }
+
+    fn in_combat(&self) -> bool {
+        !self.world.pull_on_hit && self.world.time >= self.world.in_combat
+    }
+
+    fn duration_info(&self) -> <Self::World as WorldRef<'w>>::DurationInfo {
+        ActorDurInfo { actor: *self }
+    }
+}
+
+pub struct ActorDurInfo<'w> {
+    actor: ActorHandle<'w>,
+}
+
+impl<'w> DurationInfo for ActorDurInfo<'w> {
+    fn extra_ani_lock(&self) -> u16 {
+        self.actor
+            .actor
+            .player
+            .as_ref()
+            .map(|v| v.math.ex_lock)
+            .unwrap_or_default()
+    }
+
+    fn scale(&self, duration: ScaleTime) -> u32 {
+        (if let Some(player) = self.actor.actor.player.as_ref() {
+            if duration.haste() {
+                let state = player.state.borrow();
+                let buffs = StatusSnapshot {
+                    job: player.job.effect(&state),
+                    source: self
+                        .actor
+                        .actor
+                        .statuses
+                        .values()
+                        .map(|status| status.instance),
+                    // target statuses don't play a part here.
+                    target: iter::empty(),
+                };
+                player
+                    .math
+                    .action_cast_length(duration.duration() as u64, duration.stat(), &buffs)
+            } else {
+                player.math.action_cast_length(
+                    duration.duration() as u64,
+                    duration.stat(),
+                    &StatusSnapshot::empty(),
+                )
+            }
+        } else {
+            duration.duration() as u64
+        }) as u32
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StatusIter<'w> {
+    inner: Option<map::Values<'w, (Option<ActorId>, StatusEffect), StatusEntry>>,
+}
+
+impl<'w> Iterator for StatusIter<'w> {
+    type Item = StatusInstance;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.as_mut()?.next().map(|v| v.instance)
+    }
+}
+
+struct SimEventSink<'w> {
+    source: ActorHandle<'w>,
+    rng: ActorRng<'w>,
+    event: &'w mut RadixEventQueue<SimEvent>,
+    base: u32,
+}
+
+impl<'w> SimEventSink<'w> {
+    pub fn new(
+        source: ActorHandle<'w>,
+        rng: &'w mut SimRngSource,
+        event: &'w mut RadixEventQueue<SimEvent>,
+        base: u32,
+    ) -> Self {
+        Self {
+            source,
+            rng: rng.for_actor(source.id),
+            event,
+            base,
+        }
+    }
+}
+
+impl<'w> EventSink<'w> for SimEventSink<'w> {
+    type World = &'w WorldState;
+    type Actor = ActorHandle<'w>;
+    type Rng = ActorRng<'w>;
+
+    fn source(&self) -> ActorHandle<'w> {
+        self.source
+    }
+
+    fn event(&mut self, event: Event, delay: u32) {
+        let time = self.base + delay;
+        self.event.push(time, SimEvent::Event(event));
+    }
+
+    fn rng(&mut self) -> &mut Self::Rng {
+        &mut self.rng
+    }
+}
@@ -0,0 +1,339 @@
+use std::{any::TypeId, collections::HashMap, fmt, fs, io};
+
+use rand::{distributions::Bernoulli, prelude::Distribution, Rng, RngCore, SeedableRng};
+use rand_pcg::Pcg64;
+use xivc_core::{
+    math::HitTypeHandle,
+    world::{ActorId, CriticalHit, DamageVariance, DirectHit, EventRng},
+};
+
+use crate::data::{RngConfig, RollStrategy};
+
+// hit chances are in thousandths.
+const CHANCE_SCALE: f64 = 1000.0;
+
+/// The bits a forced roll is made from.
+///
+/// Sampling a distribution with these picks the same end of its range every time,
+/// which is used to force the outcome of a roll without knowing how it is made.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedRoll(pub u64);
+
+impl FixedRoll {
+    // the lowest possible roll. anything with a chance of happening will happen.
+    pub const HIT: Self = Self(0);
+    // the highest possible roll. anything that isn't certain won't happen.
+    pub const MISS: Self = Self(u64::MAX);
+    // the middle of the range, which is the average damage variance.
+    pub const MIDDLE: Self = Self(1 << 63);
+}
+
+// the draws a forced roll gets before it gives up and rolls randomly.
+const FORCED_DRAWS: u32 = 32;
+
+/// A random number generator that makes a [`FixedRoll`].
+///
+/// Samplers that reject some values and draw again, like `gen_range`, would loop forever on a
+/// rejected value. Every draw after the first moves a bit further toward the middle instead,
+/// which keeps the roll at the same end of the range, and once there have been too many draws
+/// the bits come from `fallback`.
+pub struct ForcedRng<'a, R: RngCore> {
+    roll: FixedRoll,
+    draws: u32,
+    fallback: &'a mut R,
+}
+
+impl<'a, R: RngCore> ForcedRng<'a, R> {
+    pub fn new(roll: FixedRoll, fallback: &'a mut R) -> Self {
+        Self {
+            roll,
+            draws: 0,
+            fallback,
+        }
+    }
+}
+
+impl<R: RngCore> RngCore for ForcedRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.draws >= FORCED_DRAWS {
+            return self.fallback.next_u64();
+        }
+        // the top 32 bits move by 0, 1, 3, 7, ... so both 32 and 64 bit samplers see a change.
+        let offset = ((1u64 << self.draws) - 1) << 32;
+        self.draws += 1;
+        let middle = FixedRoll::MIDDLE.0;
+        match self.roll.0 {
+            v if v >= middle => v.saturating_sub(offset).max(middle),
+            v => v.saturating_add(offset).min(middle),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_be_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// one side of a critical or direct hit roll.
+#[derive(Clone, Copy)]
+enum Outcome {
+    Force,
+    Roll(u16, FixedRoll),
+}
+
+fn outcomes(chance: Option<u16>) -> Vec<(f64, Outcome)> {
+    match chance {
+        None => vec![(1.0, Outcome::Force)],
+        Some(chance) => {
+            let p = (chance as f64 / CHANCE_SCALE).clamp(0.0, 1.0);
+            vec![
+                (p, Outcome::Roll(chance, FixedRoll::HIT)),
+                (1.0 - p, Outcome::Roll(chance, FixedRoll::MISS)),
+            ]
+        }
+    }
+}
+
+/// The average of `damage` over every combination of critical and direct hits,
+/// weighted by their chance and with no damage variance.
+///
+/// `crit` and `dhit` are the chances of each hit type, or `None` if it is forced.
+pub fn expected_damage<V>(
+    crit: Option<u16>,
+    dhit: Option<u16>,
+    damage: impl Fn(HitTypeHandle, HitTypeHandle, V) -> u64,
+) -> u64
+where
+    DamageVariance: Distribution<V>,
+{
+    // only used if a forced roll is rejected too many times, so any seed does.
+    let mut fallback = Pcg64::seed_from_u64(0);
+    let mut total = 0.0;
+    for (crit_p, crit) in outcomes(crit) {
+        for (dhit_p, dhit) in outcomes(dhit) {
+            let p = crit_p * dhit_p;
+            if p == 0.0 {
+                continue;
+            }
+            let ch = match crit {
+                Outcome::Force => HitTypeHandle::Force,
+                Outcome::Roll(chance, roll) => {
+                    CriticalHit::new(chance).sample(&mut ForcedRng::new(roll, &mut fallback))
+                }
+            };
+            let dh = match dhit {
+                Outcome::Force => HitTypeHandle::Force,
+                Outcome::Roll(chance, roll) => {
+                    DirectHit::new(chance).sample(&mut ForcedRng::new(roll, &mut fallback))
+                }
+            };
+            let variance =
+                DamageVariance::new().sample(&mut ForcedRng::new(FixedRoll::MIDDLE, &mut fallback));
+            total += p * damage(ch, dh, variance) as f64;
+        }
+    }
+    total.round() as u64
+}
+
+/// The source of every random roll in a simulation.
+///
+/// Each kind of roll for each actor gets its own stream, all seeded from the same seed.
+/// This way, a change that adds or removes a roll only shifts the rolls after it in
+/// that one stream, instead of every roll after it in the simulation.
+#[derive(Clone, Debug)]
+pub struct SimRngSource {
+    seed: u64,
+    streams: HashMap<(Purpose, ActorId), Pcg64>,
+    crit: Roller,
+    dhit: Roller,
+    variance: Roller,
+    proc: Roller,
+    partner: Roller,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Purpose {
+    Crit,
+    DirectHit,
+    Variance,
+    Proc,
+    Partner,
+}
+
+#[derive(Debug)]
+pub enum SequenceError {
+    Io(String, io::Error),
+    // the file and the roll that couldn't be read.
+    BadRoll(String, String),
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "couldn't read the roll sequence `{}`: {}", path, e),
+            Self::BadRoll(path, roll) => write!(
+                f,
+                "`{}` in the roll sequence `{}` isn't `hit`, `miss` or a number from 0 to 1",
+                roll, path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
+impl SimRngSource {
+    pub fn new(seed: u64, config: &RngConfig) -> Result<Self, SequenceError> {
+        Ok(Self {
+            seed,
+            streams: HashMap::new(),
+            crit: Roller::new(&config.crit)?,
+            dhit: Roller::new(&config.dhit)?,
+            variance: Roller::new(&config.variance)?,
+            proc: Roller::new(&config.proc)?,
+            partner: Roller::new(&config.partner)?,
+        })
+    }
+
+    /// The rolls made by or on behalf of `actor`.
+    pub fn for_actor(&mut self, actor: ActorId) -> ActorRng<'_> {
+        ActorRng {
+            source: self,
+            actor,
+        }
+    }
+
+    fn sample<D: Distribution<T>, T>(&mut self, purpose: Purpose, actor: ActorId, distr: D) -> T {
+        let roller = match purpose {
+            Purpose::Crit => &mut self.crit,
+            Purpose::DirectHit => &mut self.dhit,
+            Purpose::Variance => &mut self.variance,
+            Purpose::Proc => &mut self.proc,
+            Purpose::Partner => &mut self.partner,
+        };
+        let seed = self.seed;
+        let rng = self
+            .streams
+            .entry((purpose, actor))
+            .or_insert_with(|| Pcg64::seed_from_u64(stream_seed(seed, purpose, actor)));
+        roller.sample(rng, distr)
+    }
+}
+
+// mixes the purpose and actor into the seed. `seed_from_u64` scrambles this,
+// so streams from similar seeds aren't related.
+fn stream_seed(seed: u64, purpose: Purpose, actor: ActorId) -> u64 {
+    let key = ((purpose as u64) << 16) | actor.0 as u64;
+    seed ^ key.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// The rolls for a single actor.
+pub struct ActorRng<'a> {
+    source: &'a mut SimRngSource,
+    actor: ActorId,
+}
+
+impl<'a> ActorRng<'a> {
+    /// Rolls for the simulated dance partner, which happens with `chance`.
+    pub fn partner(&mut self, chance: f64) -> bool {
+        self.source.sample(
+            Purpose::Partner,
+            self.actor,
+            Bernoulli::new(chance).unwrap(),
+        )
+    }
+}
+
+impl<'a> EventRng for ActorRng<'a> {
+    fn random<D, T>(&mut self, distr: D) -> T
+    where
+        D: Distribution<T> + 'static,
+        T: 'static,
+    {
+        let id = TypeId::of::<D>();
+        let purpose = if id == TypeId::of::<CriticalHit>() {
+            Purpose::Crit
+        } else if id == TypeId::of::<DirectHit>() {
+            Purpose::DirectHit
+        } else if id == TypeId::of::<DamageVariance>() {
+            Purpose::Variance
+        } else {
+            // anything else comes from a job, which is a proc.
+            Purpose::Proc
+        };
+        self.source.sample(purpose, self.actor, distr)
+    }
+}
+
+// how the rolls for a single category are made.
+#[derive(Clone, Debug)]
+enum Roller {
+    Random,
+    Fixed(FixedRoll),
+    // the rolls, and the index of the next one.
+    Sequence(Vec<FixedRoll>, usize),
+}
+
+impl Roller {
+    fn new(strategy: &RollStrategy) -> Result<Self, SequenceError> {
+        Ok(match strategy {
+            RollStrategy::Random => Self::Random,
+            RollStrategy::Always => Self::Fixed(FixedRoll::HIT),
+            RollStrategy::Never => Self::Fixed(FixedRoll::MISS),
+            RollStrategy::Average => Self::Fixed(FixedRoll::MIDDLE),
+            RollStrategy::Sequence(path) => Self::Sequence(read_sequence(path)?, 0),
+        })
+    }
+
+    fn sample<D: Distribution<T>, T>(&mut self, rng: &mut Pcg64, distr: D) -> T {
+        match self {
+            Self::Random => rng.sample(distr),
+            Self::Fixed(roll) => distr.sample(&mut ForcedRng::new(*roll, rng)),
+            Self::Sequence(rolls, next) => match rolls.get(*next) {
+                Some(roll) => {
+                    *next += 1;
+                    distr.sample(&mut ForcedRng::new(*roll, rng))
+                }
+                // once the sequence runs out, everything is random again.
+                None => rng.sample(distr),
+            },
+        }
+    }
+}
+
+/// Reads the rolls of a [`RollStrategy::Sequence`] file at `path`.
+pub fn read_sequence(path: &str) -> Result<Vec<FixedRoll>, SequenceError> {
+    let file = fs::read_to_string(path).map_err(|e| SequenceError::Io(path.to_string(), e))?;
+    file.split_whitespace()
+        .map(|roll| {
+            parse_roll(roll)
+                .ok_or_else(|| SequenceError::BadRoll(path.to_string(), roll.to_string()))
+        })
+        .collect()
+}
+
+// a roll is either `hit`, `miss`, or a number between 0 and 1 for where
+// in the range of the distribution it should land.
+fn parse_roll(roll: &str) -> Option<FixedRoll> {
+    match roll {
+        "hit" => Some(FixedRoll::HIT),
+        "miss" => Some(FixedRoll::MISS),
+        _ => {
+            let v: f64 = roll.parse().ok()?;
+            (0.0..=1.0)
+                .contains(&v)
+                .then_some(FixedRoll((v * u64::MAX as f64) as u64))
+        }
+    }
+}
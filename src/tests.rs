@@ -0,0 +1,1149 @@
+use std::{cell::RefCell, env, fmt::Write, fs, future::Future, path::Path, rc::Rc};
+
+use serde::Deserialize;
+use xivc_core::{
+    enums::Job,
+    job::{self, dnc::DncAction, dnc::TECHNICAL_FINISH},
+    world::{
+        status::{StatusEvent, StatusEventKind, StatusInstance},
+        Action, ActorId, Event,
+    },
+};
+
+use crate::{
+    actions, analysis, batch,
+    config::Format,
+    data::{PartyConfig, SimData},
+    jobai::{Controller, JobAiCoro, Selected, Wait},
+    validate, CastBar, CastFailure, CombatTime, DamageCategory, DamageSource, DynJobAi, ReportData,
+    ReportKind, SimEvent, Simulation, StatusEntry, StatusReportKind,
+};
+
+// a single dancer with the default ai.
+const DNC: &str = r#"
+in_combat = 15000
+end = 60000
+players = [
+    {
+        name = "Dancer"
+        job = "DNC"
+        first_auto_attack = 14000
+        stats = {
+            dex = 4589
+            crt = 3033
+            det = 2195
+            dh = 2271
+            sks = 420
+        }
+        weapon = {
+            wd = 141
+            auto = 14664
+            delay = 312
+        }
+        player_info = {
+            clan = "Moon"
+            job = "DNC"
+            lvl = 100
+        }
+    }
+]
+enemies = [
+    {
+        name = "Enemy"
+    }
+]
+"#;
+
+// nothing but a single enemy, for testing the world on its own.
+const ENEMY: &str = r#"
+in_combat = 0
+end = 60000
+players = []
+enemies = [
+    {
+        name = "Enemy"
+    }
+]
+"#;
+
+const SEED: u64 = 1;
+
+// builds a simulation from the config, returning it and the time it should end.
+fn build(config: &str) -> (Simulation, u32) {
+    let mut deserializer = ycf::de::TopDeserializer::from_str(config);
+    let data = SimData::deserialize(&mut deserializer).unwrap();
+    let end = data.end + data.pull();
+    (Simulation::from_sim_data(data, SEED).unwrap(), end)
+}
+
+fn run(sim: &mut Simulation, end: u32) {
+    sim.run(end);
+}
+
+fn render(sim: &Simulation) -> String {
+    let mut out = String::new();
+    for (time, kind) in &sim.log {
+        writeln!(
+            out,
+            "{:>9}: {}",
+            CombatTime::new(*time, &sim.world),
+            ReportData {
+                kind,
+                world: &sim.world
+            }
+        )
+        .unwrap();
+    }
+    out
+}
+
+// compares the full report log against `tests/golden/<name>.log`.
+// if `BLESS` is set, the file is written instead.
+fn check_golden(name: &str, config: &str) {
+    let (mut sim, end) = build(config);
+    run(&mut sim, end);
+    let log = render(&sim);
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.log", name));
+
+    if env::var_os("BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, log).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "couldn't read {}: {}. run with `BLESS=1` to write it",
+            path.display(),
+            e
+        )
+    });
+    for (i, (a, b)) in log.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(a, b, "{} differs at line {}", path.display(), i + 1);
+    }
+    assert_eq!(
+        log.lines().count(),
+        expected.lines().count(),
+        "{} has a different length",
+        path.display()
+    );
+}
+
+// replaces the ai of the first actor.
+fn with_ai<F, Fut>(sim: &mut Simulation, f: F)
+where
+    F: FnOnce(Controller) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    sim.actions
+        .insert(ActorId(0), DynJobAi(Box::new(JobAiCoro::new(f))));
+}
+
+fn cast_starts(sim: &Simulation) -> Vec<(u32, Action, u16)> {
+    sim.log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::CastStart { action, recast, .. } => Some((*time, *action, *recast)),
+            _ => None,
+        })
+        .collect()
+}
+
+// every time the status `name` went up or came off the first player.
+fn status_changes(sim: &Simulation, name: &str) -> Vec<(u32, bool)> {
+    sim.log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::Status {
+                status,
+                target,
+                kind,
+                ..
+            } if status.name == name && *target == ActorId(0) => match kind {
+                StatusReportKind::Apply { .. } => Some((*time, true)),
+                StatusReportKind::Remove => Some((*time, false)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn golden_dnc() {
+    check_golden("dnc", DNC);
+}
+
+#[test]
+fn same_seed_same_log() {
+    // a second player makes sure the ais and their rolls always run in the same order.
+    let player = DNC
+        .split_once("players = [\n")
+        .and_then(|(_, v)| v.split_once("\n]\nenemies"))
+        .unwrap()
+        .0;
+    let config = DNC.replace(
+        "players = [\n",
+        &format!(
+            "players = [\n{}\n",
+            player.replace("Dancer", "Second Dancer")
+        ),
+    );
+    let (mut a, end) = build(&config);
+    let (mut b, _) = build(&config);
+    run(&mut a, end);
+    run(&mut b, end);
+    assert_eq!(render(&a), render(&b));
+}
+
+// runs the test config with `settings` added, casting nothing but cascade and with no auto attacks.
+fn cascades(settings: &str, seed: u64) -> Vec<u64> {
+    let config = DNC
+        .replace("players = [", &format!("{}\nplayers = [", settings))
+        .replace("first_auto_attack = 14000", "first_auto_attack = 1000000");
+    let data = Format::Ycf.parse(&config).unwrap();
+    let mut sim = Simulation::builder(data).seed(seed).build().unwrap();
+    with_ai(&mut sim, |mut c: Controller| async move {
+        loop {
+            let _ = c.cast(DncAction::Cascade.into()).await;
+            c.wait_gcd().await;
+        }
+    });
+    run(&mut sim, 30000);
+
+    let damage: Vec<u64> = sim
+        .log
+        .iter()
+        .filter_map(|(_, kind)| match kind {
+            ReportKind::Damage { source, damage, .. } if *source == ActorId(0) => Some(*damage),
+            _ => None,
+        })
+        .collect();
+    assert!(damage.len() > 5, "{:?}", damage);
+    damage
+}
+
+#[test]
+fn forced_rolls_force_the_outcome() {
+    let never = cascades(
+        "rng = { crit = \"Never\" dhit = \"Never\" variance = \"Average\" }",
+        SEED,
+    );
+    let always = cascades(
+        "rng = { crit = \"Always\" dhit = \"Always\" variance = \"Average\" }",
+        SEED,
+    );
+    // every hit is the same, as nothing about them is random.
+    assert!(never.iter().all(|v| *v == never[0]), "{:?}", never);
+    assert!(always.iter().all(|v| *v == always[0]), "{:?}", always);
+    // a critical direct hit is at least 1.4 * 1.25 times the damage.
+    assert!(
+        always[0] * 100 >= never[0] * 175,
+        "{} {}",
+        always[0],
+        never[0]
+    );
+}
+
+#[test]
+fn sequence_rolls_run_out_into_random_ones() {
+    let path = env::temp_dir().join("xivc-text-sim-crits.txt");
+    fs::write(&path, "hit miss 1 0 0.5").unwrap();
+    let settings = |crit: &str| {
+        format!(
+            "rng = {{ crit = {} dhit = \"Never\" variance = \"Average\" }}",
+            crit
+        )
+    };
+    let sequence = cascades(
+        &settings(&format!(
+            "{{ Sequence = {:?} }}",
+            path.display().to_string()
+        )),
+        SEED,
+    );
+    let never = cascades(&settings("\"Never\""), SEED);
+    let always = cascades(&settings("\"Always\""), SEED);
+
+    assert_eq!(
+        sequence[..4],
+        [always[0], never[0], never[0], always[0]],
+        "{:?}",
+        sequence
+    );
+    // the rest of the rolls are random, but each one still finishes.
+    assert_eq!(sequence.len(), never.len());
+}
+
+#[test]
+fn expected_damage_ignores_the_seed() {
+    let a = cascades("expected_damage = true", 1);
+    let b = cascades("expected_damage = true", 2);
+    assert_eq!(a.iter().sum::<u64>(), b.iter().sum::<u64>());
+    assert!(a.iter().all(|v| *v == a[0]), "{:?}", a);
+}
+
+#[test]
+fn advance_counts_down_timers() {
+    let (mut sim, _) = build(DNC);
+    let player = sim.world.actors[0].player.as_mut().unwrap();
+    player.gcd = 2500;
+    player.lock = 600;
+
+    sim.world.advance(1000, &mut sim.events);
+
+    let player = sim.world.actors[0].player.as_ref().unwrap();
+    assert_eq!(sim.world.time, 1000);
+    assert_eq!(player.gcd, 1500);
+    assert_eq!(player.lock, 0);
+}
+
+#[test]
+fn advance_removes_expired_statuses() {
+    let (mut sim, _) = build(ENEMY);
+    let enemy = ActorId(0);
+    let key = (
+        if TECHNICAL_FINISH.unique {
+            None
+        } else {
+            Some(enemy)
+        },
+        TECHNICAL_FINISH,
+    );
+    sim.world.actors[0].statuses.insert(
+        key,
+        StatusEntry {
+            instance: StatusInstance {
+                source: enemy,
+                effect: TECHNICAL_FINISH,
+                time: 5000,
+                stack: 1,
+            },
+            snapshot: None,
+        },
+    );
+
+    sim.world.advance(2000, &mut sim.events);
+    assert_eq!(sim.world.actors[0].statuses[&key].instance.time, 3000);
+
+    sim.world.advance(4000, &mut sim.events);
+    assert!(sim.world.actors[0].statuses.is_empty());
+
+    let mut falloff = None;
+    while let Some((time, event)) = sim.events.pop() {
+        if let SimEvent::Event(Event::Status(StatusEvent {
+            kind: StatusEventKind::FallOff,
+            ..
+        })) = event
+        {
+            falloff = Some(time);
+        }
+    }
+    assert_eq!(falloff, Some(5000));
+}
+
+#[test]
+fn statuses_fall_off() {
+    let (mut sim, _) = build(ENEMY);
+    sim.events.push(
+        1000,
+        SimEvent::Event(Event::Status(StatusEvent {
+            kind: StatusEventKind::Apply {
+                duration: 5000,
+                stacks: 1,
+            },
+            source: ActorId(0),
+            status: TECHNICAL_FINISH,
+            target: ActorId(0),
+        })),
+    );
+
+    run(&mut sim, 10000);
+
+    assert!(sim.world.actors[0].statuses.is_empty());
+    let removed: Vec<u32> = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::Status {
+                kind: StatusReportKind::Remove,
+                ..
+            } => Some(*time),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(removed, vec![6000]);
+}
+
+#[test]
+fn start_cast_applies_cooldown() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+
+    let action: job::Action = DncAction::Devilment.into();
+    sim.events
+        .push(16000, SimEvent::StartCast(ActorId(0), Action::Job(action)));
+    run(&mut sim, 17000);
+
+    let until = sim
+        .log
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            ReportKind::CastStart { cooldowns, .. } => cooldowns.first().map(|v| v.1),
+            _ => None,
+        })
+        .unwrap();
+    assert!(until > 0);
+
+    let (group, cd, charges) = action.cd_info().unwrap();
+    let player = sim.world.actors[0].player.as_ref().unwrap();
+    assert_eq!(
+        player.cooldowns.get(group).unwrap().cd_until(cd, charges),
+        until - 1000
+    );
+}
+
+#[test]
+fn reduced_cooldowns_are_ready_sooner() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+
+    let action: job::Action = DncAction::Devilment.into();
+    let (group, ..) = action.cd_info().unwrap();
+    sim.events
+        .push(16000, SimEvent::StartCast(ActorId(0), Action::Job(action)));
+    sim.events.push(
+        17000,
+        SimEvent::Event(Event::AdvCd(group, 30000, ActorId(0))),
+    );
+    run(&mut sim, 18000);
+
+    let until = sim
+        .log
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            ReportKind::CastStart { cooldowns, .. } => cooldowns.first().map(|v| v.1),
+            _ => None,
+        })
+        .unwrap();
+    let player = sim.world.actors[0].player.as_ref().unwrap();
+    assert_eq!(player.cd_ready, vec![(group, 16000 + until - 30000)]);
+}
+
+#[test]
+fn start_cast_on_cooldown() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+
+    let action = Action::Job(DncAction::Devilment.into());
+    sim.events
+        .push(16000, SimEvent::StartCast(ActorId(0), action));
+    sim.events
+        .push(17000, SimEvent::StartCast(ActorId(0), action));
+    run(&mut sim, 18000);
+
+    assert_eq!(cast_starts(&sim).len(), 1);
+}
+
+#[test]
+fn controller_cast_failure() {
+    let (mut sim, _) = build(DNC);
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let r = results.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        let action: job::Action = DncAction::Devilment.into();
+        c.wait_until_time(16000).await;
+        c.cast(action).await.unwrap();
+        // skip the wait in `cast`, so the cooldown isn't ready.
+        let (time, id) = (c.ctx().time, c.ctx().id);
+        c.push(time, SimEvent::StartCast(id, Action::Job(action)));
+        let timed_out = c
+            .wait_until(
+                |c| matches!(c.ctx().event, SimEvent::CastFailed(..)),
+                Some(1000),
+            )
+            .await;
+        let failure = match &c.ctx().event {
+            SimEvent::CastFailed(_, _, failure) => Some(failure.clone()),
+            _ => None,
+        };
+        r.borrow_mut().push((timed_out, failure));
+    });
+    run(&mut sim, 20000);
+
+    let results = results.borrow();
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].0);
+    assert!(matches!(results[0].1, Some(CastFailure::Cooldown(..))));
+}
+
+#[test]
+fn controller_wait() {
+    let (mut sim, _) = build(DNC);
+    let times = Rc::new(RefCell::new(Vec::new()));
+
+    let t = times.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        t.borrow_mut().push(c.ctx().time);
+        c.wait(1500).await;
+        t.borrow_mut().push(c.ctx().time);
+    });
+    run(&mut sim, 10000);
+
+    let times = times.borrow();
+    assert_eq!(times.len(), 2);
+    assert_eq!(times[1] - times[0], 1500);
+}
+
+#[test]
+fn controller_wait_gcd() {
+    let (mut sim, _) = build(DNC);
+    // the time, gcd and lock after each wait.
+    let waits = Rc::new(RefCell::new(Vec::new()));
+
+    let w = waits.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        let record = |c: &Controller| {
+            let player = c.ctx().actor().actor.player.as_ref().unwrap();
+            (c.ctx().time, player.gcd, player.lock)
+        };
+        c.cast(DncAction::Cascade.into()).await.unwrap();
+        c.wait_lock().await;
+        w.borrow_mut().push(record(&c));
+        c.wait_before_gcd(500).await;
+        w.borrow_mut().push(record(&c));
+        c.wait_gcd().await;
+        w.borrow_mut().push(record(&c));
+    });
+    run(&mut sim, 10000);
+
+    let (start, _, recast) = cast_starts(&sim)[0];
+    let waits = waits.borrow();
+    assert_eq!(waits.len(), 3);
+
+    let (_, _, lock) = waits[0];
+    assert_eq!(lock, 0);
+
+    let (time, gcd, _) = waits[1];
+    assert_eq!(time, start + recast as u32 - 500);
+    assert_eq!(gcd, 500);
+
+    let (time, gcd, lock) = waits[2];
+    assert_eq!(time, start + recast as u32);
+    assert_eq!((gcd, lock), (0, 0));
+}
+
+#[test]
+fn other_jobs_actions_have_no_cooldown() {
+    let (mut sim, _) = build(DNC);
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let r = results.clone();
+    with_ai(&mut sim, move |c: Controller| async move {
+        for action in actions::job_action_list(Job::SAM) {
+            if action.cd_info().is_some() {
+                r.borrow_mut()
+                    .push((c.cooldown_remaining(action), c.charges(action)));
+            }
+        }
+    });
+    run(&mut sim, 1000);
+
+    let results = results.borrow();
+    assert!(!results.is_empty());
+    assert!(results.iter().all(|&v| v == (0, 0)));
+}
+
+#[test]
+fn controller_wait_until_timeout() {
+    let (mut sim, _) = build(DNC);
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let r = results.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        let start = c.ctx().time;
+        let timed_out = c.wait_until(|_| false, Some(2000)).await;
+        r.borrow_mut().push((timed_out, c.ctx().time - start));
+        let timed_out = c.wait_until(|_| true, Some(2000)).await;
+        r.borrow_mut().push((timed_out, c.ctx().time - start));
+    });
+    run(&mut sim, 10000);
+
+    assert_eq!(*results.borrow(), vec![(true, 2000), (false, 2000)]);
+}
+
+#[test]
+fn controller_waits_for_statuses_from_anyone() {
+    let (mut sim, _) = build(DNC);
+    // the enemy puts the status on the dancer.
+    sim.events.push(
+        2000,
+        SimEvent::Event(Event::Status(StatusEvent {
+            kind: StatusEventKind::Apply {
+                duration: 5000,
+                stacks: 1,
+            },
+            source: ActorId(1),
+            status: TECHNICAL_FINISH,
+            target: ActorId(0),
+        })),
+    );
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let r = results.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        let timed_out = c.wait_for_status(TECHNICAL_FINISH, Some(10000)).await;
+        r.borrow_mut().push((timed_out, c.ctx().time));
+    });
+    run(&mut sim, 20000);
+
+    assert_eq!(*results.borrow(), vec![(false, 2000)]);
+}
+
+#[test]
+fn controller_select() {
+    let (mut sim, _) = build(DNC);
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let r = results.clone();
+    with_ai(&mut sim, move |mut c: Controller| async move {
+        let selected = c.select(Wait::time(3000), Wait::time(1000)).await;
+        r.borrow_mut().push((selected, c.ctx().time));
+        let selected = c.select(Wait::time(2000), Wait::time(2000)).await;
+        r.borrow_mut().push((selected, c.ctx().time));
+    });
+    run(&mut sim, 10000);
+
+    assert_eq!(
+        *results.borrow(),
+        vec![(Selected::Second, 1000), (Selected::First, 2000)]
+    );
+}
+
+#[test]
+fn movement_interrupts_cast_bar() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+
+    // dancers don't have anything with a cast bar, so fake one.
+    let action = Action::Job(DncAction::Cascade.into());
+    let player = sim.world.actors[0].player.as_mut().unwrap();
+    player.casting = Some(CastBar {
+        action,
+        start: 0,
+        snap: 2500,
+        mp: 0,
+        cooldowns: [None; 2],
+    });
+    player.gcd = 2500;
+    sim.events
+        .push(2500, SimEvent::CastBarEnd(ActorId(0), action));
+    sim.events.push(1000, SimEvent::MoveStart(ActorId(0)));
+    sim.events.push(1500, SimEvent::MoveEnd(ActorId(0)));
+    run(&mut sim, 5000);
+
+    let player = sim.world.actors[0].player.as_ref().unwrap();
+    assert!(player.casting.is_none());
+    assert!(!player.moving);
+    assert_eq!(player.gcd, 0);
+
+    let interrupts: Vec<u32> = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::CastInterrupted { .. } => Some(*time),
+            ReportKind::CastSnap { .. } => panic!("an interrupted cast snapshotted"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(interrupts, vec![1000]);
+}
+
+#[test]
+fn held_cooldowns_drift() {
+    let (mut sim, end) = build(DNC);
+    sim.actions.clear();
+    run(&mut sim, end);
+
+    // nothing is used, so every cooldown drifts for the whole fight.
+    let (group, ..) = job::Action::from(DncAction::Devilment).cd_info().unwrap();
+    let summary = &analysis::rotation(&sim.log, &sim.world, end)[0];
+    let devilment = summary
+        .cooldowns
+        .iter()
+        .find(|cd| cd.group == group)
+        .unwrap();
+    assert_eq!(devilment.uses, 0);
+    assert_eq!(devilment.drift, end - sim.world.in_combat);
+}
+
+#[test]
+fn interrupts_give_back_mp() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+
+    let action = Action::Job(DncAction::Cascade.into());
+    let player = sim.world.actors[0].player.as_mut().unwrap();
+    player.mp = 5000;
+    player.casting = Some(CastBar {
+        action,
+        start: 0,
+        snap: 2500,
+        mp: 1000,
+        cooldowns: [None; 2],
+    });
+    sim.events
+        .push(2500, SimEvent::CastBarEnd(ActorId(0), action));
+    sim.events.push(1000, SimEvent::MoveStart(ActorId(0)));
+    run(&mut sim, 2000);
+
+    let refunds: Vec<(u32, u16, u16)> = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::MpChange { from, to, .. } => Some((*time, *from, *to)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(refunds.len(), 1);
+    let (time, from, to) = refunds[0];
+    assert_eq!((time, to - from), (1000, 1000));
+    assert_eq!(sim.world.actors[0].player.as_ref().unwrap().mp, to);
+}
+
+#[test]
+fn mp_ticks_repeat() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+    run(&mut sim, 10000);
+
+    let ticks: Vec<u32> = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::MpTick { .. } => Some(*time),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ticks, vec![0, 3000, 6000, 9000]);
+}
+
+#[test]
+fn autos_pause_while_untargetable() {
+    let (mut sim, _) = build(DNC);
+    sim.actions.clear();
+    sim.events.push(15000, SimEvent::Untargetable(ActorId(1)));
+    sim.events.push(19000, SimEvent::Targetable(ActorId(1)));
+    run(&mut sim, 23000);
+
+    let autos: Vec<u32> = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::Damage { action: None, .. } => Some(*time),
+            _ => None,
+        })
+        .collect();
+    // the weapon delay is 3.12s, and the swing due at 17.12 waits for the enemy to come back.
+    assert_eq!(autos, vec![14000, 19000, 22120]);
+}
+
+#[test]
+fn damage_is_credited_to_the_source() {
+    let (mut sim, end) = build(DNC);
+    run(&mut sim, end);
+
+    let dealt = sim.world.actors[0].dealt.total;
+    assert!(dealt > 0);
+
+    // the auto attack before the pull is left out.
+    let start = sim.world.in_combat;
+    let reported: u64 = sim
+        .log
+        .iter()
+        .filter_map(|(time, kind)| match kind {
+            ReportKind::Damage { damage, .. } | ReportKind::DotTick { damage, .. }
+                if *time >= start =>
+            {
+                Some(*damage)
+            }
+            _ => None,
+        })
+        .sum();
+    assert_eq!(dealt, reported);
+    assert!(dealt < sim.world.actors[1].damage as u64);
+}
+
+#[test]
+fn ledger_splits_damage() {
+    let (mut sim, end) = build(DNC);
+    run(&mut sim, end);
+
+    let ledger = &sim.world.actors[0].dealt;
+    let categories = [
+        DamageCategory::Direct,
+        DamageCategory::Dot,
+        DamageCategory::Auto,
+    ];
+    let by_category: u64 = categories.iter().map(|v| ledger.of_category(*v)).sum();
+    assert_eq!(by_category, ledger.total);
+    assert_eq!(ledger.to_target(ActorId(1)), ledger.total);
+    assert!(ledger
+        .entries
+        .iter()
+        .any(|v| v.source == DamageSource::Auto && v.hits > 1));
+}
+
+#[test]
+fn prepull_actions_use_pull_offsets() {
+    let config = DNC
+        .replace("in_combat = 15000", "countdown = 15000")
+        .replace(
+            "first_auto_attack = 14000",
+            "first_auto_attack = 14000\n        prepull = [[-3000 \"en_avant\"]]",
+        );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    run(&mut sim, 15000);
+
+    assert_eq!(sim.world.in_combat, 15000);
+    let starts = cast_starts(&sim);
+    assert_eq!(starts.len(), 1);
+    assert_eq!(starts[0].0, 12000);
+    assert_eq!(starts[0].1, Action::Job(DncAction::EnAvant.into()));
+}
+
+#[test]
+fn first_hit_starts_combat() {
+    let config = DNC.replace("in_combat = 15000", "in_combat = 15000\npull_on_hit = true");
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    run(&mut sim, 20000);
+
+    // the first auto attack lands before the planned pull.
+    assert_eq!(sim.world.in_combat, 14000);
+    assert!(!sim.world.pull_on_hit);
+}
+
+#[test]
+fn the_fight_follows_the_first_hit() {
+    let config = DNC
+        .replace("in_combat = 15000", "in_combat = 15000\npull_on_hit = true")
+        .replace(
+            "enemies = [",
+            "party = {
+    buffs = [{ name = \"Battle Litany\" start = 5000 duration = 15000 crit = 10 }]
+}
+enemies = [",
+        );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    sim.run_fight();
+
+    // the first auto attack lands a second before the planned pull.
+    assert_eq!(sim.end(), 14000 + 60000);
+    assert_eq!(
+        status_changes(&sim, "Battle Litany"),
+        vec![(19000, true), (34000, false)]
+    );
+}
+
+#[test]
+fn tincture_lasts_its_duration() {
+    let config = DNC.replace(
+        "first_auto_attack = 14000",
+        "first_auto_attack = 14000\n        consumables = { tincture = { cap = 392 duration = 5000 } }",
+    );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    sim.events.push(1000, SimEvent::UsePotion(ActorId(0)));
+    // still on cooldown, so this one does nothing.
+    sim.events.push(2000, SimEvent::UsePotion(ActorId(0)));
+    run(&mut sim, 10000);
+
+    assert_eq!(
+        status_changes(&sim, "Medicated"),
+        vec![(1000, true), (6000, false)]
+    );
+
+    let player = sim.world.actors[0].player.as_ref().unwrap();
+    assert_eq!(player.tincture_ready, 1000 + 270000);
+}
+
+#[test]
+fn tincture_waits_for_the_cast_bar() {
+    let config = DNC.replace(
+        "first_auto_attack = 14000",
+        "first_auto_attack = 14000\n        consumables = { tincture = { cap = 392 duration = 5000 } }",
+    );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    // dancers don't have anything with a cast bar, so fake one.
+    sim.world.actors[0].player.as_mut().unwrap().casting = Some(CastBar {
+        action: Action::Job(DncAction::Cascade.into()),
+        start: 0,
+        snap: 2000,
+        mp: 0,
+        cooldowns: [None; 2],
+    });
+    sim.events.push(1000, SimEvent::UsePotion(ActorId(0)));
+    run(&mut sim, 3000);
+
+    assert!(status_changes(&sim, "Medicated").is_empty());
+    assert_eq!(
+        sim.world.actors[0].player.as_ref().unwrap().tincture_ready,
+        0
+    );
+}
+
+#[test]
+fn buff_stubs_repeat() {
+    let config = DNC.replace(
+        "enemies = [",
+        "party = {
+    roles = [\"Tank\" \"Tank\" \"Healer\" \"PhysRanged\"]
+    buffs = [{ name = \"Battle Litany\" start = 5000 every = 20000 duration = 15000 crit = 10 }]
+}
+enemies = [",
+    );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+
+    assert_eq!(sim.world.raid_buffs.len(), 1);
+    run(&mut sim, 60000);
+
+    assert_eq!(
+        status_changes(&sim, "Battle Litany"),
+        vec![(20000, true), (35000, false), (40000, true), (55000, false),]
+    );
+    assert_eq!(sim.world.raid_bonus(&sim.world.actors[0]).crit, 0);
+}
+
+#[test]
+fn damage_buffs_multiply() {
+    let config = DNC.replace(
+        "enemies = [",
+        "party = {
+    buffs = [
+        { name = \"Divination\" start = 5000 duration = 20000 damage = 6 }
+        { name = \"Embolden\" start = 5000 duration = 20000 damage = 5 crit = 2 }
+    ]
+}
+enemies = [",
+    );
+    let (mut sim, _) = build(&config);
+    sim.actions.clear();
+    run(&mut sim, 30000);
+
+    let bonus = sim.world.raid_bonus(&sim.world.actors[0]);
+    assert_eq!(bonus.damage, 1000 * 106 / 100 * 105 / 100);
+    assert_eq!(bonus.crit, 20);
+    assert_eq!(bonus.scale(10000), 11130);
+}
+
+#[test]
+fn party_bonus_counts_roles_once() {
+    let mut deserializer = ycf::de::TopDeserializer::from_str(
+        "roles = [\"Tank\" \"Tank\" \"Healer\" \"Melee\" \"Caster\"]",
+    );
+    let party = PartyConfig::deserialize(&mut deserializer).unwrap();
+    assert_eq!(party.bonus(), 4);
+}
+
+#[test]
+fn validate_accepts_the_test_config() {
+    let mut deserializer = ycf::de::TopDeserializer::from_str(DNC);
+    let data = SimData::deserialize(&mut deserializer).unwrap();
+    let errors = validate::validate(&data, DNC, Format::Ycf);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn validate_reports_every_error() {
+    let config = DNC
+        .replace("end = 60000", "end = 0")
+        .replace(
+            "first_auto_attack = 14000",
+            "first_auto_attack = 14000\n        prepull = [[-3000 \"en_avnt\"]]",
+        )
+        .replace(
+            "name = \"Enemy\"",
+            "name = \"Enemy\"\n        untarget = [[10000 20000] [15000 25000]]",
+        );
+    let mut deserializer = ycf::de::TopDeserializer::from_str(&config);
+    let data = SimData::deserialize(&mut deserializer).unwrap();
+    let errors = validate::validate(&data, &config, Format::Ycf);
+
+    let paths: Vec<&str> = errors.iter().map(|v| v.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec!["end", "players[0].prepull[0]", "enemies[0].untarget[1]"]
+    );
+    assert!(
+        errors[1].message.contains("did you mean `en_avant`?"),
+        "{}",
+        errors[1].message
+    );
+    // every error can be found in the file.
+    assert!(errors.iter().all(|v| v.line.is_some()));
+}
+
+#[test]
+fn validate_checks_prepull_buffs_and_rolls() {
+    let bad = env::temp_dir().join("xivc-text-sim-bad-rolls.txt");
+    fs::write(&bad, "hit sometimes").unwrap();
+    let missing = env::temp_dir().join("xivc-text-sim-missing-rolls.txt");
+    let _ = fs::remove_file(&missing);
+    let config = DNC
+        .replace(
+            "first_auto_attack = 14000",
+            "first_auto_attack = 14000\n        prepull = [[-20000 \"en_avant\"]]",
+        )
+        .replace(
+            "enemies = [",
+            &format!(
+                "party = {{
+    buffs = [{{ name = \"Nothing\" duration = 0 every = 0 }}]
+}}
+rng = {{ crit = {{ Sequence = {:?} }} dhit = {{ Sequence = {:?} }} }}
+enemies = [",
+                bad.display().to_string(),
+                missing.display().to_string()
+            ),
+        );
+    let data = Format::Ycf.parse(&config).unwrap();
+    let errors = validate::validate(&data, &config, Format::Ycf);
+
+    let paths: Vec<&str> = errors.iter().map(|v| v.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "players[0].prepull[0]",
+            "party.buffs[0].duration",
+            "party.buffs[0].every",
+            "party.buffs[0]",
+            "rng.crit",
+            "rng.dhit",
+        ]
+    );
+    assert!(errors.iter().all(|v| v.line.is_some()), "{:?}", errors);
+    assert!(errors[4].message.contains("sometimes"));
+
+    // running it fails with the same problem instead of panicking.
+    let error = Simulation::from_sim_data(data, SEED).err().unwrap();
+    assert!(error.to_string().contains("sometimes"), "{}", error);
+}
+
+#[test]
+fn validate_reports_unknown_keys() {
+    let config = DNC
+        .replace("end = 60000", "end = 60000\nin_comabt = 1000")
+        .replace("delay = 312", "delay = 312\n            dealy = 312");
+    let data = Format::Ycf.parse(&config).unwrap();
+    let errors = validate::validate(&data, &config, Format::Ycf);
+
+    let found: Vec<(&str, &str)> = errors
+        .iter()
+        .map(|v| {
+            (
+                v.path.as_str(),
+                config.lines().nth(v.line.unwrap() - 1).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("in_comabt", "in_comabt = 1000"),
+            ("players[0].weapon.dealy", "            dealy = 312"),
+        ]
+    );
+}
+
+#[test]
+fn validate_finds_lines_in_every_format() {
+    let mut data = Format::Ycf.parse(DNC).unwrap();
+    data.end = 0;
+    let mut second = data.players[0].clone();
+    second.name = "Second".to_string();
+    second.weapon.delay = 0;
+    second.prepull = vec![(-3000, "en_avnt".to_string())];
+    data.players.push(second);
+
+    for format in [Format::Ycf, Format::Toml, Format::Json] {
+        let source = format.write(&data).unwrap();
+        let errors = validate::validate(&data, &source, format);
+        let line = |path: &str| {
+            let error = errors.iter().find(|v| v.path == path).unwrap();
+            error
+                .line
+                .unwrap_or_else(|| panic!("{} has no line in {}", path, format))
+        };
+        let text = |line: usize| source.lines().nth(line - 1).unwrap();
+        let second = source
+            .lines()
+            .position(|v| v.contains("\"Second\""))
+            .unwrap()
+            + 1;
+
+        assert!(text(line("end")).contains("end"), "{}", format);
+        assert!(text(line("players[1].prepull[0]")).contains("en_avnt"));
+        // the first player has a weapon too, but it isn't the one with the error.
+        assert!(line("players[1].weapon.delay") > second, "{}", format);
+        assert!(text(line("players[1].weapon.delay")).contains("weapon"));
+    }
+}
+
+#[test]
+fn action_lists_match_xivc_core() {
+    let jobs = actions::job_actions();
+
+    // the checked in lists have to be exactly what `cargo run -- actions` writes.
+    for job in &jobs {
+        let path = format!("action_lists/{}.txt", job.job.to_lowercase());
+        let list = fs::read_to_string(&path).unwrap_or_default();
+        let listed: Vec<&str> = list.lines().filter(|v| !v.trim().is_empty()).collect();
+        let ids: Vec<&str> = job.actions.iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(listed, ids, "{} is out of date", path);
+    }
+    for entry in fs::read_dir("action_lists").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|v| v == "txt") {
+            let job = path.file_stem().unwrap().to_string_lossy().to_uppercase();
+            assert!(
+                jobs.iter().any(|v| v.job == job),
+                "{} isn't a job anymore",
+                path.display()
+            );
+        }
+    }
+
+    let dnc = jobs.iter().find(|v| v.job == "DNC").unwrap();
+    let cascade = dnc.actions.iter().find(|v| v.id == "cascade").unwrap();
+    assert!(cascade.gcd);
+}
+
+#[test]
+fn config_formats_round_trip() {
+    let mut data = Format::Ycf.parse(DNC).unwrap();
+    data.seed = Some(batch::MAX_SEED);
+    let json = Format::Json.write(&data).unwrap();
+
+    for format in [Format::Ycf, Format::Toml, Format::Json] {
+        let written = format.write(&data).unwrap();
+        let parsed = format.parse(&written).unwrap();
+        assert_eq!(Format::Json.write(&parsed).unwrap(), json, "{}", format);
+    }
+
+    // toml can't hold anything bigger, so it is an error everywhere.
+    data.seed = Some(batch::MAX_SEED + 1);
+    assert!(Format::Toml.write(&data).is_err());
+    let source = Format::Json.write(&data).unwrap();
+    let errors = validate::validate(&data, &source, Format::Json);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].path, "seed");
+}
@@ -0,0 +1,515 @@
+use std::fmt;
+
+use serde::de::value::{Error, StrDeserializer};
+use xivc_core::{
+    enums::Job,
+    job,
+    math::{PlayerStats, WeaponInfo},
+};
+
+use crate::{
+    actions, batch,
+    config::Format,
+    data::{ActionKind, BuffStub, PlayerData, RollStrategy, SimData},
+    rng,
+};
+
+/// A problem with a config, along with where it is.
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    // the path to the value, like `players[0].prepull[1]`.
+    pub path: String,
+    // the line in the config file, if it could be found.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}, {}: {}", line, self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+// the only level that has data for it.
+const LEVEL: u16 = 100;
+
+/// Checks `data` for everything that would only fail once it is run, or would silently be wrong.
+///
+/// `source` is the text the config was read from in `format`, which is used to find keys that
+/// aren't part of the config and the line of each error. Every error is returned instead of
+/// stopping at the first one.
+pub fn validate(data: &SimData, source: &str, format: Format) -> Vec<ConfigError> {
+    let source = Source {
+        text: source,
+        format,
+    };
+    let mut errors = Errors {
+        source,
+        list: Vec::new(),
+    };
+
+    for path in format.unknown_keys(source.text) {
+        // the key is looked for in the actor it is in, if it is in one.
+        let block = match path.split_once('[') {
+            Some(("players", rest)) => index(rest)
+                .and_then(|i| data.players.get(i))
+                .and_then(|v| source.named(&v.name)),
+            Some(("enemies", rest)) => index(rest)
+                .and_then(|i| data.enemies.get(i))
+                .and_then(|v| source.named(&v.name)),
+            Some(("party.buffs", rest)) => index(rest)
+                .and_then(|i| data.party.buffs.get(i))
+                .and_then(|v| source.named(&v.name)),
+            _ => Some(FILE),
+        };
+        let key = path.rsplit('.').next().unwrap_or(&path);
+        errors.push(
+            &path,
+            source.key_line(key, block),
+            format!("unknown key `{}`, which is ignored", key),
+        );
+    }
+
+    if data.end == 0 {
+        errors.push(
+            "end",
+            source.key_line("end", Some(FILE)),
+            "the simulation has to last longer than 0ms",
+        );
+    }
+    if data.runs == 0 {
+        errors.push(
+            "runs",
+            source.key_line("runs", Some(FILE)),
+            "at least one run is needed",
+        );
+    }
+    if data.seed.is_some_and(|v| v > batch::MAX_SEED) {
+        errors.push(
+            "seed",
+            source.key_line("seed", Some(FILE)),
+            format!("the seed can be at most {}", batch::MAX_SEED),
+        );
+    }
+    if data.enemies.is_empty() {
+        errors.push(
+            "enemies",
+            source.key_line("enemies", Some(FILE)),
+            "there has to be an enemy to attack",
+        );
+    }
+
+    for (index, player) in data.players.iter().enumerate() {
+        validate_player(&mut errors, index, player, data.pull());
+    }
+
+    for (index, buff) in data.party.buffs.iter().enumerate() {
+        validate_buff(&mut errors, index, buff);
+    }
+
+    for (name, strategy) in [
+        ("crit", &data.rng.crit),
+        ("dhit", &data.rng.dhit),
+        ("variance", &data.rng.variance),
+        ("proc", &data.rng.proc),
+        ("partner", &data.rng.partner),
+    ] {
+        if let RollStrategy::Sequence(path) = strategy {
+            if let Err(e) = rng::read_sequence(path) {
+                let quoted = format!("\"{}\"", path);
+                let line = source.line(Some(FILE), |line| line.contains(&quoted));
+                errors.push(&format!("rng.{}", name), line, e.to_string());
+            }
+        }
+    }
+
+    for (index, enemy) in data.enemies.iter().enumerate() {
+        let path = format!("enemies[{}]", index);
+        let block = source.named(&enemy.name);
+        errors.windows(&path, "untarget", block, &enemy.untarget);
+    }
+
+    errors.list
+}
+
+// the index at the start of what comes after a `[` in a path, like `0].stats`.
+fn index(rest: &str) -> Option<usize> {
+    rest.split_once(']')?.0.parse().ok()
+}
+
+fn validate_player(errors: &mut Errors, index: usize, player: &PlayerData, pull: u32) {
+    let path = format!("players[{}]", index);
+    let source = errors.source;
+    let block = source.named(&player.name);
+    let at = |key: &str| source.key_line(key, block);
+
+    // every player is controlled by the dancer ai.
+    if player.job != Job::DNC {
+        errors.push(
+            &format!("{}.job", path),
+            at("job"),
+            format!(
+                "{:?} has no ai. only DNC players can be simulated",
+                player.job
+            ),
+        );
+    }
+    if player.player_info.job != player.job {
+        errors.push(
+            &format!("{}.player_info.job", path),
+            at("player_info"),
+            format!(
+                "{:?} doesn't match the player's job, {:?}",
+                player.player_info.job, player.job
+            ),
+        );
+    }
+    if player.player_info.lvl as u16 != LEVEL {
+        errors.push(
+            &format!("{}.player_info.lvl", path),
+            at("lvl"),
+            format!("only level {} is supported", LEVEL),
+        );
+    }
+
+    validate_stats(errors, &path, at("stats"), &player.stats);
+    validate_weapon(errors, &path, at("weapon"), &player.weapon);
+
+    for (i, action) in player.actions.iter().enumerate() {
+        let name = match action {
+            ActionKind::Normal(name) | ActionKind::Delay(_, name) => name,
+        };
+        errors.action(&format!("{}.actions[{}]", path, i), block, player.job, name);
+    }
+    for (i, (offset, name)) in player.prepull.iter().enumerate() {
+        let item = format!("{}.prepull[{}]", path, i);
+        if name != "tincture" {
+            errors.action(&item, block, player.job, name);
+        }
+        // nothing can be used before the simulation starts, which is `pull` before the pull.
+        if pull as i64 + (*offset as i64) < 0 {
+            let quoted = format!("\"{}\"", name);
+            errors.push(
+                &item,
+                source.line(block, |line| line.contains(&quoted)),
+                format!(
+                    "the simulation only starts {}ms before the pull, so this would be used then",
+                    pull
+                ),
+            );
+        }
+    }
+
+    errors.windows(&path, "movement", block, &player.movement);
+    errors.windows(&path, "disengage", block, &player.disengage);
+}
+
+fn validate_buff(errors: &mut Errors, index: usize, buff: &BuffStub) {
+    let path = format!("party.buffs[{}]", index);
+    let source = errors.source;
+    let block = source.named(&buff.name);
+    let at = |key: &str| source.key_line(key, block);
+
+    if buff.duration == 0 {
+        errors.push(
+            &format!("{}.duration", path),
+            at("duration"),
+            "the buff has to last longer than 0ms",
+        );
+    }
+    if buff.every == Some(0) {
+        errors.push(
+            &format!("{}.every", path),
+            at("every"),
+            "the buff can't be used every 0ms. leave `every` out to use it once",
+        );
+    }
+    if buff.crit == 0 && buff.dhit == 0 && buff.damage == 0 {
+        errors.push(
+            &path,
+            block.map(|v| v.start),
+            "the buff doesn't raise `crit`, `dhit` or `damage`",
+        );
+    }
+}
+
+fn validate_stats(errors: &mut Errors, path: &str, line: Option<usize>, stats: &PlayerStats) {
+    // substats never go below their base at a level.
+    let base = PlayerStats::default(LEVEL as _);
+    for (name, value, min) in [
+        ("crt", stats.crt, base.crt),
+        ("det", stats.det, base.det),
+        ("dh", stats.dh, base.dh),
+        ("sks", stats.sks, base.sks),
+        ("sps", stats.sps, base.sps),
+        ("ten", stats.ten, base.ten),
+        ("pie", stats.pie, base.pie),
+    ] {
+        if value < min {
+            errors.push(
+                &format!("{}.stats.{}", path, name),
+                line,
+                format!("{} is below the base of {} at level {}", value, min, LEVEL),
+            );
+        }
+    }
+}
+
+fn validate_weapon(errors: &mut Errors, path: &str, line: Option<usize>, weapon: &WeaponInfo) {
+    if weapon.delay == 0 {
+        errors.push(
+            &format!("{}.weapon.delay", path),
+            line,
+            "auto attacks need a delay above 0",
+        );
+    }
+}
+
+struct Errors<'a> {
+    source: Source<'a>,
+    list: Vec<ConfigError>,
+}
+
+impl Errors<'_> {
+    fn push(&mut self, path: &str, line: Option<usize>, message: impl Into<String>) {
+        self.list.push(ConfigError {
+            path: path.to_string(),
+            line,
+            message: message.into(),
+        });
+    }
+
+    fn action(&mut self, path: &str, block: Option<Block>, job: Job, name: &str) {
+        if job::Action::deserialize_for(job, StrDeserializer::<Error>::new(name)).is_ok() {
+            return;
+        }
+        let list = format!("action_lists/{}.txt", format!("{:?}", job).to_lowercase());
+        let message = match suggest(job, name) {
+            Some(close) => format!(
+                "unknown {:?} action `{}`. did you mean `{}`?",
+                job, name, close
+            ),
+            None => format!(
+                "unknown {:?} action `{}`. the valid names are in `{}`",
+                job, name, list
+            ),
+        };
+        let quoted = format!("\"{}\"", name);
+        let line = self.source.line(block, |line| line.contains(&quoted));
+        self.push(path, line, message);
+    }
+
+    // checks that every window ends after it starts, and that none of them overlap.
+    fn windows(&mut self, path: &str, key: &str, block: Option<Block>, windows: &[(u32, u32)]) {
+        let line = self.source.key_line(key, block);
+        let mut sorted: Vec<_> = windows.iter().enumerate().collect();
+        sorted.sort_by_key(|(_, window)| window.0);
+
+        for (i, &(from, to)) in windows.iter().enumerate() {
+            if to <= from {
+                self.push(
+                    &format!("{}.{}[{}]", path, key, i),
+                    line,
+                    format!("the window ends at {} before it starts at {}", to, from),
+                );
+            }
+        }
+        for pair in sorted.windows(2) {
+            let ((a, first), (b, second)) = (pair[0], pair[1]);
+            if second.0 < first.1 {
+                self.push(
+                    &format!("{}.{}[{}]", path, key, b),
+                    line,
+                    format!("overlaps with {}[{}], which ends at {}", key, a, first.1),
+                );
+            }
+        }
+    }
+}
+
+// the 1 based lines a part of the config is on, including both ends.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    start: usize,
+    end: usize,
+}
+
+const FILE: Block = Block {
+    start: 1,
+    end: usize::MAX,
+};
+
+// the text of a config, which finds where things are in it.
+#[derive(Clone, Copy)]
+struct Source<'a> {
+    text: &'a str,
+    format: Format,
+}
+
+impl Source<'_> {
+    // the first line in `block` that `matches`, skipping comments.
+    // nothing is found without a block, so a key is never found in the wrong actor.
+    fn line(&self, block: Option<Block>, matches: impl Fn(&str) -> bool) -> Option<usize> {
+        let block = block?;
+        let comment = match self.format {
+            Format::Ycf => Some("//"),
+            Format::Toml => Some("#"),
+            Format::Json => None,
+        };
+        self.text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .skip(block.start - 1)
+            .take_while(|(i, _)| *i <= block.end)
+            // comments are full of examples.
+            .filter(|(_, line)| !matches!(comment, Some(v) if line.trim_start().starts_with(v)))
+            .find(|(_, line)| matches(line))
+            .map(|(i, _)| i)
+    }
+
+    // the first line in `block` that sets `key`.
+    fn key_line(&self, key: &str, block: Option<Block>) -> Option<usize> {
+        self.line(block, |line| sets_key(self.format, line, key))
+    }
+
+    // the lines of the actor or party buff named `name`. these are the ones between the braces
+    // around its name, or the `[[players]]`, `[[enemies]]` or `[[party.buffs]]` table it is in
+    // for toml, along with the tables inside that one.
+    fn named(&self, name: &str) -> Option<Block> {
+        let quoted = format!("\"{}\"", name);
+        let name_line = self.line(Some(FILE), |line| line.contains(&quoted))?;
+        match self.format {
+            Format::Toml => {
+                let lines: Vec<&str> = self.text.lines().collect();
+                let start = lines[..name_line].iter().rposition(|line| {
+                    line.trim_start().starts_with("[[") && toml_header(line).is_some()
+                })?;
+                let inner = format!("{}.", toml_header(lines[start])?);
+                let end = lines[name_line..]
+                    .iter()
+                    .position(|line| matches!(toml_header(line), Some(v) if !v.starts_with(&inner)))
+                    .map_or(lines.len(), |i| name_line + i);
+                Some(Block {
+                    start: start + 1,
+                    end,
+                })
+            }
+            Format::Ycf | Format::Json => {
+                // the offset of the name and not just its line, as an actor can be on one line.
+                let line_start: usize = self
+                    .text
+                    .split_inclusive('\n')
+                    .take(name_line - 1)
+                    .map(str::len)
+                    .sum();
+                let offset = line_start + self.text[line_start..].find(&quoted)?;
+                let (open, close) = braces_around(self.text, self.format, offset)?;
+                let line_at = |offset: usize| self.text[..offset].matches('\n').count() + 1;
+                Some(Block {
+                    start: line_at(open),
+                    end: line_at(close),
+                })
+            }
+        }
+    }
+}
+
+// if `line` sets `key`, like `key =` in ycf and toml or `"key":` in json.
+fn sets_key(format: Format, line: &str, key: &str) -> bool {
+    if format == Format::Json {
+        let quoted = format!("\"{}\"", key);
+        return line
+            .match_indices(&quoted)
+            .any(|(i, v)| line[i + v.len()..].trim_start().starts_with(':'));
+    }
+    // toml writes nested tables as headers, like `[players.stats]`.
+    if format == Format::Toml
+        && matches!(toml_header(line), Some(v) if v.rsplit('.').next() == Some(key))
+    {
+        return true;
+    }
+    line.match_indices(key).any(|(i, _)| {
+        let before = line[..i].chars().next_back();
+        let after = line[i + key.len()..].trim_start();
+        !matches!(before, Some(c) if c.is_alphanumeric() || c == '_') && after.starts_with('=')
+    })
+}
+
+// the name of the table a toml header starts, like `players.stats` for `[players.stats]`.
+fn toml_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let name = line
+        .strip_prefix("[[")
+        .and_then(|v| v.strip_suffix("]]"))
+        .or_else(|| line.strip_prefix('[').and_then(|v| v.strip_suffix(']')))?;
+    let valid = |c: char| c.is_alphanumeric() || c == '_' || c == '-' || c == '.';
+    (!name.is_empty() && name.chars().all(valid)).then_some(name)
+}
+
+// the offsets of the innermost `{` and `}` around `offset`, skipping strings and comments.
+fn braces_around(text: &str, format: Format, offset: usize) -> Option<(usize, usize)> {
+    let mut open = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if format == Format::Ycf && matches!(chars.peek(), Some((_, '/'))) => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' => open.push(i),
+            '}' => match open.pop() {
+                Some(start) if start < offset && i > offset => return Some((start, i)),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    None
+}
+
+// the closest action of `job` to `name`, if it is close enough to be a typo.
+fn suggest(job: Job, name: &str) -> Option<String> {
+    let job = format!("{:?}", job);
+    let actions = actions::job_actions().into_iter().find(|v| v.job == job)?;
+    actions
+        .actions
+        .into_iter()
+        .map(|action| (distance(&action.id, name), action.id))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, id)| id)
+}
+
+// the edit distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(prev + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}